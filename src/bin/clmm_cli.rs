@@ -0,0 +1,246 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use clap::{Parser, Subcommand};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{bs58, pubkey::Pubkey};
+use swap_io_clmm_rust_sdk::{
+    decoder::ClmmInstruction,
+    pool::PoolManager,
+    quote::QuoteCalculator,
+    snapshot::PoolSnapshot,
+    utils::{amount_with_slippage, sqrt_price_x64_to_decimal_price},
+};
+
+#[derive(Parser)]
+#[command(name = "clmm-cli", about = "Inspect and quote Swap.io CLMM pools")]
+struct Cli {
+    /// Rpc endpoint used when no snapshot is given
+    #[arg(long, default_value = "https://api.mainnet-beta.solana.com")]
+    rpc_url: String,
+    /// Load the pool from a snapshot file instead of rpc
+    #[arg(long)]
+    snapshot: Option<PathBuf>,
+    /// Pool state address, required when loading over rpc
+    #[arg(long)]
+    pool: Option<Pubkey>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print price, liquidity, tick, fee rates and vaults
+    Pool,
+    /// List the initialized tick arrays and whether they are loaded
+    TickArrays,
+    /// Quote a swap
+    Quote {
+        #[arg(long)]
+        input_mint: Pubkey,
+        #[arg(long)]
+        amount: u64,
+        /// Treat the amount as the exact output instead of the exact input
+        #[arg(long)]
+        exact_out: bool,
+        #[arg(long, default_value_t = 0)]
+        slippage_bps: u16,
+    },
+    /// Decode instruction data, base58 unless --base64 is given
+    DecodeInstruction {
+        data: String,
+        #[arg(long)]
+        base64: bool,
+    },
+    /// Save the loaded pool to a snapshot file, json if the path ends in .json
+    Snapshot { out: PathBuf },
+}
+
+fn load_pool_manager(cli: &Cli) -> Result<PoolManager> {
+    if let Some(snapshot) = &cli.snapshot {
+        return PoolSnapshot::load(snapshot)?.to_pool_manager();
+    }
+    let pool_key = cli
+        .pool
+        .ok_or_else(|| anyhow::anyhow!("--pool is required without --snapshot"))?;
+    let rpc_client = RpcClient::new(cli.rpc_url.clone());
+    let mut pool_manager = PoolManager::fetch(&rpc_client, pool_key)?;
+    pool_manager.fetch_vaults(&rpc_client)?;
+    Ok(pool_manager)
+}
+
+fn print_pool(pool_manager: &PoolManager) -> Result<()> {
+    let pool_state = &pool_manager.pool_state;
+    println!("pool:              {}", pool_manager.pool_key);
+    println!("program:           {}", pool_manager.program_id);
+    println!("amm config:        {}", pool_state.amm_config);
+    println!(
+        "mint 0:            {} ({} decimals)",
+        pool_state.token_mint_0, pool_state.mint_decimals_0
+    );
+    println!(
+        "mint 1:            {} ({} decimals)",
+        pool_state.token_mint_1, pool_state.mint_decimals_1
+    );
+    println!("vault 0:           {}", pool_state.token_vault_0);
+    println!("vault 1:           {}", pool_state.token_vault_1);
+    println!("observation:       {}", pool_state.observation_key);
+    println!("tick spacing:      {}", pool_state.tick_spacing);
+    println!("tick current:      {}", pool_state.tick_current);
+    println!("sqrt price x64:    {}", pool_state.sqrt_price_x64);
+    println!(
+        "price:             {} mint 1 per mint 0",
+        sqrt_price_x64_to_decimal_price(
+            pool_state.sqrt_price_x64,
+            pool_state.mint_decimals_0,
+            pool_state.mint_decimals_1
+        )?
+    );
+    println!("liquidity:         {}", pool_state.liquidity);
+    println!(
+        "status:            {:#010b} {:?}",
+        pool_state.status,
+        pool_manager.status()
+    );
+    println!("open time:         {}", pool_state.open_time);
+    if let Some(amm_config) = &pool_manager.amm_config {
+        // fee rates are in hundredths of a bip (10^-6)
+        println!(
+            "trade fee rate:    {} ({}%)",
+            amm_config.trade_fee_rate,
+            amm_config.trade_fee_rate as f64 / 10_000.0
+        );
+        println!("protocol fee rate: {}", amm_config.protocol_fee_rate);
+        println!("fund fee rate:     {}", amm_config.fund_fee_rate);
+    }
+    if let Ok(reserves) = pool_manager.reserves() {
+        println!(
+            "reserve 0:         {} (vault {}, protocol fees {}, fund fees {})",
+            reserves.reserve_0(),
+            reserves.vault_amount_0,
+            reserves.protocol_fees_0,
+            reserves.fund_fees_0
+        );
+        println!(
+            "reserve 1:         {} (vault {}, protocol fees {}, fund fees {})",
+            reserves.reserve_1(),
+            reserves.vault_amount_1,
+            reserves.protocol_fees_1,
+            reserves.fund_fees_1
+        );
+        if let Ok(tvl) = pool_manager.tvl() {
+            println!("tvl:               {} mint 1", tvl);
+        }
+    }
+    if let Some((min_slot, max_slot)) = pool_manager.get_slot_range() {
+        println!("slots:             {}..={}", min_slot, max_slot);
+    }
+    Ok(())
+}
+
+fn print_tick_arrays(pool_manager: &PoolManager) -> Result<()> {
+    let loaded: Vec<_> = pool_manager
+        .up_tick_arrays
+        .iter()
+        .chain(pool_manager.down_tick_arrays.iter())
+        .collect();
+    println!(
+        "{:>12}  {:<44}  {}",
+        "start index", "address", "initialized ticks"
+    );
+    for start_index in pool_manager.get_initialized_tick_array_start_indexes()? {
+        let initialized_ticks = loaded
+            .iter()
+            .find(|tick_array| tick_array.start_tick_index == start_index)
+            .map(|tick_array| tick_array.initialized_tick_count.to_string())
+            .unwrap_or_else(|| "not loaded".to_string());
+        println!(
+            "{:>12}  {:<44}  {}",
+            start_index,
+            pool_manager.tick_array(start_index).to_string(),
+            initialized_ticks
+        );
+    }
+    Ok(())
+}
+
+fn print_quote(
+    pool_manager: &PoolManager,
+    input_mint: Pubkey,
+    amount: u64,
+    exact_out: bool,
+    slippage_bps: u16,
+) -> Result<()> {
+    let pool_state = &pool_manager.pool_state;
+    let output_mint = if input_mint == pool_state.token_mint_0 {
+        pool_state.token_mint_1
+    } else if input_mint == pool_state.token_mint_1 {
+        pool_state.token_mint_0
+    } else {
+        return Err(anyhow::anyhow!("{} is not a mint of this pool", input_mint));
+    };
+    let quote = QuoteCalculator::calculate_quote(
+        input_mint,
+        output_mint,
+        !exact_out,
+        amount,
+        pool_manager,
+    )?;
+    let slippage = slippage_bps as f64 / 10_000.0;
+    println!("input mint:  {}", input_mint);
+    println!("output mint: {}", output_mint);
+    println!("in amount:   {}", quote.in_amount);
+    println!("out amount:  {}", quote.out_amount);
+    println!(
+        "fee amount:  {} (lp {}, protocol {}, fund {})",
+        quote.fee_amount, quote.lp_fee_amount, quote.protocol_fee_amount, quote.fund_fee_amount
+    );
+    println!("fee pct:     {}", quote.fee_pct);
+    if exact_out {
+        println!(
+            "max in:      {}",
+            amount_with_slippage(quote.in_amount, slippage, true)
+        );
+    } else {
+        println!(
+            "min out:     {}",
+            amount_with_slippage(quote.out_amount, slippage, false)
+        );
+    }
+    Ok(())
+}
+
+fn decode_instruction(data: &str, is_base64: bool) -> Result<()> {
+    let data = if is_base64 {
+        STANDARD.decode(data)?
+    } else {
+        bs58::decode(data).into_vec()?
+    };
+    let instruction = ClmmInstruction::decode(&data)?;
+    println!("{}", instruction.name());
+    println!("{:#?}", instruction);
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    match &cli.command {
+        Command::DecodeInstruction { data, base64 } => decode_instruction(data, *base64),
+        Command::Pool => print_pool(&load_pool_manager(&cli)?),
+        Command::TickArrays => print_tick_arrays(&load_pool_manager(&cli)?),
+        Command::Quote {
+            input_mint,
+            amount,
+            exact_out,
+            slippage_bps,
+        } => print_quote(
+            &load_pool_manager(&cli)?,
+            *input_mint,
+            *amount,
+            *exact_out,
+            *slippage_bps,
+        ),
+        Command::Snapshot { out } => load_pool_manager(&cli)?.to_snapshot()?.save(out),
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm_rust_sdk::{
+    instruction::{EncodedInstruction, InstructionBuilder},
+    quote::{Quote, QuoteConfig},
+    registry::PoolRegistry,
+    utils::{amount_with_slippage, serde_pubkey},
+};
+
+#[derive(Parser)]
+#[command(
+    name = "clmm-server",
+    about = "Serve quotes and swap instructions over http"
+)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+    /// Rpc endpoint the pools are loaded and refreshed from
+    #[arg(long, default_value = "https://api.mainnet-beta.solana.com")]
+    rpc_url: String,
+    /// Pool state address to serve, may be repeated
+    #[arg(long = "pool")]
+    pools: Vec<Pubkey>,
+    /// Serve pools from snapshot files instead of rpc, may be repeated
+    #[arg(long = "snapshot")]
+    snapshots: Vec<PathBuf>,
+    /// Milliseconds between refreshes of the rpc pools
+    #[arg(long, default_value_t = 2_000)]
+    refresh_interval_ms: u64,
+}
+
+type SharedRegistry = Arc<RwLock<PoolRegistry>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Side {
+    ExactIn,
+    ExactOut,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuoteRequest {
+    /// Quote on this pool, otherwise on the best loaded pool for the pair
+    #[serde(default, with = "optional_pubkey")]
+    pool: Option<Pubkey>,
+    #[serde(with = "serde_pubkey")]
+    input_mint: Pubkey,
+    #[serde(with = "serde_pubkey")]
+    output_mint: Pubkey,
+    amount: u64,
+    side: Side,
+    #[serde(default)]
+    slippage_bps: u16,
+}
+
+#[derive(Debug, Serialize)]
+struct QuoteResponse {
+    #[serde(with = "serde_pubkey")]
+    pool: Pubkey,
+    quote: Quote,
+    /// Minimum output for exact-in, maximum input for exact-out
+    other_amount_threshold: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SwapInstructionRequest {
+    #[serde(flatten)]
+    quote: QuoteRequest,
+    #[serde(with = "serde_pubkey")]
+    payer: Pubkey,
+    #[serde(with = "serde_pubkey")]
+    source_token_account: Pubkey,
+    #[serde(with = "serde_pubkey")]
+    destination_token_account: Pubkey,
+}
+
+#[derive(Debug, Serialize)]
+struct SwapInstructionResponse {
+    #[serde(flatten)]
+    quote: QuoteResponse,
+    instruction: EncodedInstruction,
+}
+
+mod optional_pubkey {
+    use serde::{Deserialize, Deserializer};
+    use solana_sdk::pubkey::Pubkey;
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Pubkey>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|key| key.parse().map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+struct ApiError(anyhow::Error);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.0.to_string() });
+        (StatusCode::BAD_REQUEST, Json(body)).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        ApiError(err)
+    }
+}
+
+fn unix_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn compute_quote(registry: &PoolRegistry, request: &QuoteRequest) -> Result<QuoteResponse> {
+    let base_in = request.side == Side::ExactIn;
+    let (pool, quote) = registry.quote(
+        request.pool,
+        request.input_mint,
+        request.output_mint,
+        base_in,
+        request.amount,
+        &QuoteConfig {
+            epoch: registry.epoch(),
+            open_time_check: Some(unix_timestamp()),
+            ui_amount_timestamp: Some(unix_timestamp()),
+            ..QuoteConfig::default()
+        },
+    )?;
+    let slippage = request.slippage_bps as f64 / 10_000.0;
+    let other_amount_threshold = if base_in {
+        amount_with_slippage(quote.out_amount, slippage, false)
+    } else {
+        amount_with_slippage(quote.in_amount, slippage, true)
+    };
+    Ok(QuoteResponse {
+        pool,
+        quote,
+        other_amount_threshold,
+    })
+}
+
+async fn health(State(registry): State<SharedRegistry>) -> Json<Vec<String>> {
+    let registry = registry.read().unwrap();
+    Json(
+        registry
+            .pool_keys()
+            .iter()
+            .map(|key| key.to_string())
+            .collect(),
+    )
+}
+
+async fn quote(
+    State(registry): State<SharedRegistry>,
+    Json(request): Json<QuoteRequest>,
+) -> Result<Json<QuoteResponse>, ApiError> {
+    let registry = registry.read().unwrap();
+    Ok(Json(compute_quote(&registry, &request)?))
+}
+
+async fn swap_instruction(
+    State(registry): State<SharedRegistry>,
+    Json(request): Json<SwapInstructionRequest>,
+) -> Result<Json<SwapInstructionResponse>, ApiError> {
+    let registry = registry.read().unwrap();
+    let quote = compute_quote(&registry, &request.quote)?;
+    let pool_manager = registry
+        .get(&quote.pool)
+        .ok_or_else(|| anyhow::anyhow!("Pool {} not loaded", quote.pool))?;
+    let instruction = InstructionBuilder::build_swap_v2_instruction_at(
+        pool_manager,
+        request.payer,
+        request.quote.input_mint,
+        request.quote.output_mint,
+        request.source_token_account,
+        request.destination_token_account,
+        request.quote.amount,
+        quote.other_amount_threshold,
+        None,
+        request.quote.side == Side::ExactIn,
+        Some(unix_timestamp()),
+    )?;
+    Ok(Json(SwapInstructionResponse {
+        instruction: EncodedInstruction::from(&instruction),
+        quote,
+    }))
+}
+
+// refreshes a copy of the registry so quotes are never blocked on rpc
+fn spawn_refresh(registry: SharedRegistry, rpc_client: RpcClient, interval: Duration) {
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(interval);
+            let mut refreshed = registry.read().unwrap().clone();
+            for (pool_key, err) in refreshed.refresh(&rpc_client) {
+                eprintln!("failed to refresh {}: {}", pool_key, err);
+            }
+            *registry.write().unwrap() = refreshed;
+        }
+    });
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let registry = if args.snapshots.is_empty() {
+        let rpc_client = RpcClient::new(args.rpc_url.clone());
+        let registry = Arc::new(RwLock::new(PoolRegistry::fetch(&rpc_client, &args.pools)?));
+        spawn_refresh(
+            registry.clone(),
+            rpc_client,
+            Duration::from_millis(args.refresh_interval_ms),
+        );
+        registry
+    } else {
+        // snapshot pools are served as they were saved
+        Arc::new(RwLock::new(PoolRegistry::load_snapshots(&args.snapshots)?))
+    };
+    if registry.read().unwrap().is_empty() {
+        return Err(anyhow::anyhow!(
+            "No pools configured, pass --pool or --snapshot"
+        ));
+    }
+
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/quote", post(quote))
+        .route("/swap-instruction", post(swap_instruction))
+        .with_state(registry);
+    let listener = tokio::net::TcpListener::bind(args.listen).await?;
+    println!("listening on {}", args.listen);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
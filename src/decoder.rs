@@ -0,0 +1,786 @@
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::Result;
+use solana_sdk::{
+    instruction::{CompiledInstruction, Instruction},
+    pubkey::Pubkey,
+};
+use swap_io_clmm::{instruction as clmm_instruction, states::POOL_TICK_ARRAY_BITMAP_SEED};
+
+/// What an account is to the instruction, named after the program's account structs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccountRole {
+    Payer,
+    AmmConfig,
+    PoolState,
+    InputTokenAccount,
+    OutputTokenAccount,
+    InputVault,
+    OutputVault,
+    ObservationState,
+    TokenProgram,
+    TokenProgram2022,
+    MemoProgram,
+    InputVaultMint,
+    OutputVaultMint,
+    InputTokenMint,
+    PositionNftOwner,
+    PositionNftMint,
+    PositionNftAccount,
+    MetadataAccount,
+    ProtocolPosition,
+    TickArrayLower,
+    TickArrayUpper,
+    PersonalPosition,
+    TokenAccount0,
+    TokenAccount1,
+    TokenVault0,
+    TokenVault1,
+    Rent,
+    SystemProgram,
+    AssociatedTokenProgram,
+    MetadataProgram,
+    Vault0Mint,
+    Vault1Mint,
+    NftOwner,
+    NftAccount,
+    RecipientTokenAccount0,
+    RecipientTokenAccount1,
+    Owner,
+    PoolCreator,
+    TokenMint0,
+    TokenMint1,
+    TickArrayBitmap,
+    TokenProgram0,
+    TokenProgram1,
+    Authority,
+    // accounts after the fixed ones: tick arrays, the bitmap extension, rewards or router hops
+    TickArray,
+    TickArrayBitmapExtension,
+    Remaining,
+}
+
+impl AccountRole {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AccountRole::Payer => "payer",
+            AccountRole::AmmConfig => "amm_config",
+            AccountRole::PoolState => "pool_state",
+            AccountRole::InputTokenAccount => "input_token_account",
+            AccountRole::OutputTokenAccount => "output_token_account",
+            AccountRole::InputVault => "input_vault",
+            AccountRole::OutputVault => "output_vault",
+            AccountRole::ObservationState => "observation_state",
+            AccountRole::TokenProgram => "token_program",
+            AccountRole::TokenProgram2022 => "token_program_2022",
+            AccountRole::MemoProgram => "memo_program",
+            AccountRole::InputVaultMint => "input_vault_mint",
+            AccountRole::OutputVaultMint => "output_vault_mint",
+            AccountRole::InputTokenMint => "input_token_mint",
+            AccountRole::PositionNftOwner => "position_nft_owner",
+            AccountRole::PositionNftMint => "position_nft_mint",
+            AccountRole::PositionNftAccount => "position_nft_account",
+            AccountRole::MetadataAccount => "metadata_account",
+            AccountRole::ProtocolPosition => "protocol_position",
+            AccountRole::TickArrayLower => "tick_array_lower",
+            AccountRole::TickArrayUpper => "tick_array_upper",
+            AccountRole::PersonalPosition => "personal_position",
+            AccountRole::TokenAccount0 => "token_account_0",
+            AccountRole::TokenAccount1 => "token_account_1",
+            AccountRole::TokenVault0 => "token_vault_0",
+            AccountRole::TokenVault1 => "token_vault_1",
+            AccountRole::Rent => "rent",
+            AccountRole::SystemProgram => "system_program",
+            AccountRole::AssociatedTokenProgram => "associated_token_program",
+            AccountRole::MetadataProgram => "metadata_program",
+            AccountRole::Vault0Mint => "vault_0_mint",
+            AccountRole::Vault1Mint => "vault_1_mint",
+            AccountRole::NftOwner => "nft_owner",
+            AccountRole::NftAccount => "nft_account",
+            AccountRole::RecipientTokenAccount0 => "recipient_token_account_0",
+            AccountRole::RecipientTokenAccount1 => "recipient_token_account_1",
+            AccountRole::Owner => "owner",
+            AccountRole::PoolCreator => "pool_creator",
+            AccountRole::TokenMint0 => "token_mint_0",
+            AccountRole::TokenMint1 => "token_mint_1",
+            AccountRole::TickArrayBitmap => "tick_array_bitmap",
+            AccountRole::TokenProgram0 => "token_program_0",
+            AccountRole::TokenProgram1 => "token_program_1",
+            AccountRole::Authority => "authority",
+            AccountRole::TickArray => "tick_array",
+            AccountRole::TickArrayBitmapExtension => "tick_array_bitmap_extension",
+            AccountRole::Remaining => "remaining",
+        }
+    }
+}
+
+impl std::fmt::Display for AccountRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+const SWAP_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::Payer,
+    AccountRole::AmmConfig,
+    AccountRole::PoolState,
+    AccountRole::InputTokenAccount,
+    AccountRole::OutputTokenAccount,
+    AccountRole::InputVault,
+    AccountRole::OutputVault,
+    AccountRole::ObservationState,
+    AccountRole::TokenProgram,
+    AccountRole::TickArray,
+];
+
+const SWAP_V2_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::Payer,
+    AccountRole::AmmConfig,
+    AccountRole::PoolState,
+    AccountRole::InputTokenAccount,
+    AccountRole::OutputTokenAccount,
+    AccountRole::InputVault,
+    AccountRole::OutputVault,
+    AccountRole::ObservationState,
+    AccountRole::TokenProgram,
+    AccountRole::TokenProgram2022,
+    AccountRole::MemoProgram,
+    AccountRole::InputVaultMint,
+    AccountRole::OutputVaultMint,
+];
+
+// followed by one group of pool accounts per hop
+const SWAP_ROUTER_BASE_IN_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::Payer,
+    AccountRole::InputTokenAccount,
+    AccountRole::InputTokenMint,
+    AccountRole::TokenProgram,
+    AccountRole::TokenProgram2022,
+    AccountRole::MemoProgram,
+];
+
+const OPEN_POSITION_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::Payer,
+    AccountRole::PositionNftOwner,
+    AccountRole::PositionNftMint,
+    AccountRole::PositionNftAccount,
+    AccountRole::MetadataAccount,
+    AccountRole::PoolState,
+    AccountRole::ProtocolPosition,
+    AccountRole::TickArrayLower,
+    AccountRole::TickArrayUpper,
+    AccountRole::PersonalPosition,
+    AccountRole::TokenAccount0,
+    AccountRole::TokenAccount1,
+    AccountRole::TokenVault0,
+    AccountRole::TokenVault1,
+    AccountRole::Rent,
+    AccountRole::SystemProgram,
+    AccountRole::TokenProgram,
+    AccountRole::AssociatedTokenProgram,
+    AccountRole::MetadataProgram,
+];
+
+const OPEN_POSITION_V2_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::Payer,
+    AccountRole::PositionNftOwner,
+    AccountRole::PositionNftMint,
+    AccountRole::PositionNftAccount,
+    AccountRole::MetadataAccount,
+    AccountRole::PoolState,
+    AccountRole::ProtocolPosition,
+    AccountRole::TickArrayLower,
+    AccountRole::TickArrayUpper,
+    AccountRole::PersonalPosition,
+    AccountRole::TokenAccount0,
+    AccountRole::TokenAccount1,
+    AccountRole::TokenVault0,
+    AccountRole::TokenVault1,
+    AccountRole::Rent,
+    AccountRole::SystemProgram,
+    AccountRole::TokenProgram,
+    AccountRole::AssociatedTokenProgram,
+    AccountRole::MetadataProgram,
+    AccountRole::TokenProgram2022,
+    AccountRole::Vault0Mint,
+    AccountRole::Vault1Mint,
+];
+
+const OPEN_POSITION_WITH_TOKEN22_NFT_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::Payer,
+    AccountRole::PositionNftOwner,
+    AccountRole::PositionNftMint,
+    AccountRole::PositionNftAccount,
+    AccountRole::PoolState,
+    AccountRole::ProtocolPosition,
+    AccountRole::TickArrayLower,
+    AccountRole::TickArrayUpper,
+    AccountRole::PersonalPosition,
+    AccountRole::TokenAccount0,
+    AccountRole::TokenAccount1,
+    AccountRole::TokenVault0,
+    AccountRole::TokenVault1,
+    AccountRole::Rent,
+    AccountRole::SystemProgram,
+    AccountRole::TokenProgram,
+    AccountRole::AssociatedTokenProgram,
+    AccountRole::TokenProgram2022,
+    AccountRole::Vault0Mint,
+    AccountRole::Vault1Mint,
+];
+
+const INCREASE_LIQUIDITY_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::NftOwner,
+    AccountRole::NftAccount,
+    AccountRole::PoolState,
+    AccountRole::ProtocolPosition,
+    AccountRole::PersonalPosition,
+    AccountRole::TickArrayLower,
+    AccountRole::TickArrayUpper,
+    AccountRole::TokenAccount0,
+    AccountRole::TokenAccount1,
+    AccountRole::TokenVault0,
+    AccountRole::TokenVault1,
+    AccountRole::TokenProgram,
+];
+
+const INCREASE_LIQUIDITY_V2_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::NftOwner,
+    AccountRole::NftAccount,
+    AccountRole::PoolState,
+    AccountRole::ProtocolPosition,
+    AccountRole::PersonalPosition,
+    AccountRole::TickArrayLower,
+    AccountRole::TickArrayUpper,
+    AccountRole::TokenAccount0,
+    AccountRole::TokenAccount1,
+    AccountRole::TokenVault0,
+    AccountRole::TokenVault1,
+    AccountRole::TokenProgram,
+    AccountRole::TokenProgram2022,
+    AccountRole::Vault0Mint,
+    AccountRole::Vault1Mint,
+];
+
+const DECREASE_LIQUIDITY_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::NftOwner,
+    AccountRole::NftAccount,
+    AccountRole::PersonalPosition,
+    AccountRole::PoolState,
+    AccountRole::ProtocolPosition,
+    AccountRole::TokenVault0,
+    AccountRole::TokenVault1,
+    AccountRole::TickArrayLower,
+    AccountRole::TickArrayUpper,
+    AccountRole::RecipientTokenAccount0,
+    AccountRole::RecipientTokenAccount1,
+    AccountRole::TokenProgram,
+];
+
+const DECREASE_LIQUIDITY_V2_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::NftOwner,
+    AccountRole::NftAccount,
+    AccountRole::PersonalPosition,
+    AccountRole::PoolState,
+    AccountRole::ProtocolPosition,
+    AccountRole::TokenVault0,
+    AccountRole::TokenVault1,
+    AccountRole::TickArrayLower,
+    AccountRole::TickArrayUpper,
+    AccountRole::RecipientTokenAccount0,
+    AccountRole::RecipientTokenAccount1,
+    AccountRole::TokenProgram,
+    AccountRole::TokenProgram2022,
+    AccountRole::MemoProgram,
+    AccountRole::Vault0Mint,
+    AccountRole::Vault1Mint,
+];
+
+const CLOSE_POSITION_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::NftOwner,
+    AccountRole::PositionNftMint,
+    AccountRole::PositionNftAccount,
+    AccountRole::PersonalPosition,
+    AccountRole::SystemProgram,
+    AccountRole::TokenProgram,
+];
+
+const CREATE_AMM_CONFIG_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::Owner,
+    AccountRole::AmmConfig,
+    AccountRole::SystemProgram,
+];
+
+const UPDATE_AMM_CONFIG_ACCOUNTS: &[AccountRole] = &[AccountRole::Owner, AccountRole::AmmConfig];
+
+const CREATE_POOL_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::PoolCreator,
+    AccountRole::AmmConfig,
+    AccountRole::PoolState,
+    AccountRole::TokenMint0,
+    AccountRole::TokenMint1,
+    AccountRole::TokenVault0,
+    AccountRole::TokenVault1,
+    AccountRole::ObservationState,
+    AccountRole::TickArrayBitmap,
+    AccountRole::TokenProgram0,
+    AccountRole::TokenProgram1,
+    AccountRole::SystemProgram,
+    AccountRole::Rent,
+];
+
+const UPDATE_POOL_STATUS_ACCOUNTS: &[AccountRole] =
+    &[AccountRole::Authority, AccountRole::PoolState];
+
+const COLLECT_FEE_ACCOUNTS: &[AccountRole] = &[
+    AccountRole::Owner,
+    AccountRole::PoolState,
+    AccountRole::AmmConfig,
+    AccountRole::TokenVault0,
+    AccountRole::TokenVault1,
+    AccountRole::Vault0Mint,
+    AccountRole::Vault1Mint,
+    AccountRole::RecipientTokenAccount0,
+    AccountRole::RecipientTokenAccount1,
+    AccountRole::TokenProgram,
+    AccountRole::TokenProgram2022,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapArgs {
+    pub amount: u64,
+    pub other_amount_threshold: u64,
+    pub sqrt_price_limit_x64: u128,
+    pub is_base_input: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapRouterBaseInArgs {
+    pub amount_in: u64,
+    pub amount_out_minimum: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenPositionArgs {
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub tick_array_lower_start_index: i32,
+    pub tick_array_upper_start_index: i32,
+    pub liquidity: u128,
+    pub amount_0_max: u64,
+    pub amount_1_max: u64,
+    // not an argument of open_position, which always writes metadata
+    pub with_metadata: bool,
+    pub base_flag: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncreaseLiquidityArgs {
+    pub liquidity: u128,
+    pub amount_0_max: u64,
+    pub amount_1_max: u64,
+    pub base_flag: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecreaseLiquidityArgs {
+    pub liquidity: u128,
+    pub amount_0_min: u64,
+    pub amount_1_min: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreateAmmConfigArgs {
+    pub index: u16,
+    pub tick_spacing: u16,
+    pub trade_fee_rate: u32,
+    pub protocol_fee_rate: u32,
+    pub fund_fee_rate: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollectFeeArgs {
+    pub amount_0_requested: u64,
+    pub amount_1_requested: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClmmInstruction {
+    Swap(SwapArgs),
+    SwapV2(SwapArgs),
+    SwapRouterBaseIn(SwapRouterBaseInArgs),
+    OpenPosition(OpenPositionArgs),
+    OpenPositionV2(OpenPositionArgs),
+    OpenPositionWithToken22Nft(OpenPositionArgs),
+    IncreaseLiquidity(IncreaseLiquidityArgs),
+    IncreaseLiquidityV2(IncreaseLiquidityArgs),
+    DecreaseLiquidity(DecreaseLiquidityArgs),
+    DecreaseLiquidityV2(DecreaseLiquidityArgs),
+    ClosePosition,
+    CreateAmmConfig(CreateAmmConfigArgs),
+    UpdateAmmConfig {
+        param: u8,
+        value: u32,
+    },
+    CreatePool {
+        sqrt_price_x64: u128,
+        open_time: u64,
+    },
+    UpdatePoolStatus {
+        status: u8,
+    },
+    CollectProtocolFee(CollectFeeArgs),
+    CollectFundFee(CollectFeeArgs),
+}
+
+impl ClmmInstruction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClmmInstruction::Swap(_) => "swap",
+            ClmmInstruction::SwapV2(_) => "swap_v2",
+            ClmmInstruction::SwapRouterBaseIn(_) => "swap_router_base_in",
+            ClmmInstruction::OpenPosition(_) => "open_position",
+            ClmmInstruction::OpenPositionV2(_) => "open_position_v2",
+            ClmmInstruction::OpenPositionWithToken22Nft(_) => "open_position_with_token22_nft",
+            ClmmInstruction::IncreaseLiquidity(_) => "increase_liquidity",
+            ClmmInstruction::IncreaseLiquidityV2(_) => "increase_liquidity_v2",
+            ClmmInstruction::DecreaseLiquidity(_) => "decrease_liquidity",
+            ClmmInstruction::DecreaseLiquidityV2(_) => "decrease_liquidity_v2",
+            ClmmInstruction::ClosePosition => "close_position",
+            ClmmInstruction::CreateAmmConfig(_) => "create_amm_config",
+            ClmmInstruction::UpdateAmmConfig { .. } => "update_amm_config",
+            ClmmInstruction::CreatePool { .. } => "create_pool",
+            ClmmInstruction::UpdatePoolStatus { .. } => "update_pool_status",
+            ClmmInstruction::CollectProtocolFee(_) => "collect_protocol_fee",
+            ClmmInstruction::CollectFundFee(_) => "collect_fund_fee",
+        }
+    }
+
+    pub fn is_swap(&self) -> bool {
+        matches!(
+            self,
+            ClmmInstruction::Swap(_)
+                | ClmmInstruction::SwapV2(_)
+                | ClmmInstruction::SwapRouterBaseIn(_)
+        )
+    }
+
+    fn account_roles(&self) -> &'static [AccountRole] {
+        match self {
+            ClmmInstruction::Swap(_) => SWAP_ACCOUNTS,
+            ClmmInstruction::SwapV2(_) => SWAP_V2_ACCOUNTS,
+            ClmmInstruction::SwapRouterBaseIn(_) => SWAP_ROUTER_BASE_IN_ACCOUNTS,
+            ClmmInstruction::OpenPosition(_) => OPEN_POSITION_ACCOUNTS,
+            ClmmInstruction::OpenPositionV2(_) => OPEN_POSITION_V2_ACCOUNTS,
+            ClmmInstruction::OpenPositionWithToken22Nft(_) => {
+                OPEN_POSITION_WITH_TOKEN22_NFT_ACCOUNTS
+            }
+            ClmmInstruction::IncreaseLiquidity(_) => INCREASE_LIQUIDITY_ACCOUNTS,
+            ClmmInstruction::IncreaseLiquidityV2(_) => INCREASE_LIQUIDITY_V2_ACCOUNTS,
+            ClmmInstruction::DecreaseLiquidity(_) => DECREASE_LIQUIDITY_ACCOUNTS,
+            ClmmInstruction::DecreaseLiquidityV2(_) => DECREASE_LIQUIDITY_V2_ACCOUNTS,
+            ClmmInstruction::ClosePosition => CLOSE_POSITION_ACCOUNTS,
+            ClmmInstruction::CreateAmmConfig(_) => CREATE_AMM_CONFIG_ACCOUNTS,
+            ClmmInstruction::UpdateAmmConfig { .. } => UPDATE_AMM_CONFIG_ACCOUNTS,
+            ClmmInstruction::CreatePool { .. } => CREATE_POOL_ACCOUNTS,
+            ClmmInstruction::UpdatePoolStatus { .. } => UPDATE_POOL_STATUS_ACCOUNTS,
+            ClmmInstruction::CollectProtocolFee(_) | ClmmInstruction::CollectFundFee(_) => {
+                COLLECT_FEE_ACCOUNTS
+            }
+        }
+    }
+
+    /// Decode the discriminator and arguments of instruction data.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 {
+            return Err(anyhow::anyhow!(
+                "Instruction data shorter than a discriminator"
+            ));
+        }
+        let (discriminator, mut args) = data.split_at(8);
+        let args = &mut args;
+        let instruction = if discriminator == clmm_instruction::Swap::DISCRIMINATOR {
+            let swap = clmm_instruction::Swap::deserialize(args)?;
+            ClmmInstruction::Swap(SwapArgs {
+                amount: swap.amount,
+                other_amount_threshold: swap.other_amount_threshold,
+                sqrt_price_limit_x64: swap.sqrt_price_limit_x64,
+                is_base_input: swap.is_base_input,
+            })
+        } else if discriminator == clmm_instruction::SwapV2::DISCRIMINATOR {
+            let swap = clmm_instruction::SwapV2::deserialize(args)?;
+            ClmmInstruction::SwapV2(SwapArgs {
+                amount: swap.amount,
+                other_amount_threshold: swap.other_amount_threshold,
+                sqrt_price_limit_x64: swap.sqrt_price_limit_x64,
+                is_base_input: swap.is_base_input,
+            })
+        } else if discriminator == clmm_instruction::SwapRouterBaseIn::DISCRIMINATOR {
+            let swap = clmm_instruction::SwapRouterBaseIn::deserialize(args)?;
+            ClmmInstruction::SwapRouterBaseIn(SwapRouterBaseInArgs {
+                amount_in: swap.amount_in,
+                amount_out_minimum: swap.amount_out_minimum,
+            })
+        } else if discriminator == clmm_instruction::OpenPosition::DISCRIMINATOR {
+            let open = clmm_instruction::OpenPosition::deserialize(args)?;
+            ClmmInstruction::OpenPosition(OpenPositionArgs {
+                tick_lower_index: open.tick_lower_index,
+                tick_upper_index: open.tick_upper_index,
+                tick_array_lower_start_index: open.tick_array_lower_start_index,
+                tick_array_upper_start_index: open.tick_array_upper_start_index,
+                liquidity: open.liquidity,
+                amount_0_max: open.amount_0_max,
+                amount_1_max: open.amount_1_max,
+                with_metadata: true,
+                base_flag: None,
+            })
+        } else if discriminator == clmm_instruction::OpenPositionV2::DISCRIMINATOR {
+            let open = clmm_instruction::OpenPositionV2::deserialize(args)?;
+            ClmmInstruction::OpenPositionV2(OpenPositionArgs {
+                tick_lower_index: open.tick_lower_index,
+                tick_upper_index: open.tick_upper_index,
+                tick_array_lower_start_index: open.tick_array_lower_start_index,
+                tick_array_upper_start_index: open.tick_array_upper_start_index,
+                liquidity: open.liquidity,
+                amount_0_max: open.amount_0_max,
+                amount_1_max: open.amount_1_max,
+                with_metadata: open.with_metadata,
+                base_flag: open.base_flag,
+            })
+        } else if discriminator == clmm_instruction::OpenPositionWithToken22Nft::DISCRIMINATOR {
+            let open = clmm_instruction::OpenPositionWithToken22Nft::deserialize(args)?;
+            ClmmInstruction::OpenPositionWithToken22Nft(OpenPositionArgs {
+                tick_lower_index: open.tick_lower_index,
+                tick_upper_index: open.tick_upper_index,
+                tick_array_lower_start_index: open.tick_array_lower_start_index,
+                tick_array_upper_start_index: open.tick_array_upper_start_index,
+                liquidity: open.liquidity,
+                amount_0_max: open.amount_0_max,
+                amount_1_max: open.amount_1_max,
+                with_metadata: open.with_metadata,
+                base_flag: open.base_flag,
+            })
+        } else if discriminator == clmm_instruction::IncreaseLiquidity::DISCRIMINATOR {
+            let increase = clmm_instruction::IncreaseLiquidity::deserialize(args)?;
+            ClmmInstruction::IncreaseLiquidity(IncreaseLiquidityArgs {
+                liquidity: increase.liquidity,
+                amount_0_max: increase.amount_0_max,
+                amount_1_max: increase.amount_1_max,
+                base_flag: None,
+            })
+        } else if discriminator == clmm_instruction::IncreaseLiquidityV2::DISCRIMINATOR {
+            let increase = clmm_instruction::IncreaseLiquidityV2::deserialize(args)?;
+            ClmmInstruction::IncreaseLiquidityV2(IncreaseLiquidityArgs {
+                liquidity: increase.liquidity,
+                amount_0_max: increase.amount_0_max,
+                amount_1_max: increase.amount_1_max,
+                base_flag: increase.base_flag,
+            })
+        } else if discriminator == clmm_instruction::DecreaseLiquidity::DISCRIMINATOR {
+            let decrease = clmm_instruction::DecreaseLiquidity::deserialize(args)?;
+            ClmmInstruction::DecreaseLiquidity(DecreaseLiquidityArgs {
+                liquidity: decrease.liquidity,
+                amount_0_min: decrease.amount_0_min,
+                amount_1_min: decrease.amount_1_min,
+            })
+        } else if discriminator == clmm_instruction::DecreaseLiquidityV2::DISCRIMINATOR {
+            let decrease = clmm_instruction::DecreaseLiquidityV2::deserialize(args)?;
+            ClmmInstruction::DecreaseLiquidityV2(DecreaseLiquidityArgs {
+                liquidity: decrease.liquidity,
+                amount_0_min: decrease.amount_0_min,
+                amount_1_min: decrease.amount_1_min,
+            })
+        } else if discriminator == clmm_instruction::ClosePosition::DISCRIMINATOR {
+            ClmmInstruction::ClosePosition
+        } else if discriminator == clmm_instruction::CreateAmmConfig::DISCRIMINATOR {
+            let create = clmm_instruction::CreateAmmConfig::deserialize(args)?;
+            ClmmInstruction::CreateAmmConfig(CreateAmmConfigArgs {
+                index: create.index,
+                tick_spacing: create.tick_spacing,
+                trade_fee_rate: create.trade_fee_rate,
+                protocol_fee_rate: create.protocol_fee_rate,
+                fund_fee_rate: create.fund_fee_rate,
+            })
+        } else if discriminator == clmm_instruction::UpdateAmmConfig::DISCRIMINATOR {
+            let update = clmm_instruction::UpdateAmmConfig::deserialize(args)?;
+            ClmmInstruction::UpdateAmmConfig {
+                param: update.param,
+                value: update.value,
+            }
+        } else if discriminator == clmm_instruction::CreatePool::DISCRIMINATOR {
+            let create = clmm_instruction::CreatePool::deserialize(args)?;
+            ClmmInstruction::CreatePool {
+                sqrt_price_x64: create.sqrt_price_x64,
+                open_time: create.open_time,
+            }
+        } else if discriminator == clmm_instruction::UpdatePoolStatus::DISCRIMINATOR {
+            let update = clmm_instruction::UpdatePoolStatus::deserialize(args)?;
+            ClmmInstruction::UpdatePoolStatus {
+                status: update.status,
+            }
+        } else if discriminator == clmm_instruction::CollectProtocolFee::DISCRIMINATOR {
+            let collect = clmm_instruction::CollectProtocolFee::deserialize(args)?;
+            ClmmInstruction::CollectProtocolFee(CollectFeeArgs {
+                amount_0_requested: collect.amount_0_requested,
+                amount_1_requested: collect.amount_1_requested,
+            })
+        } else if discriminator == clmm_instruction::CollectFundFee::DISCRIMINATOR {
+            let collect = clmm_instruction::CollectFundFee::deserialize(args)?;
+            ClmmInstruction::CollectFundFee(CollectFeeArgs {
+                amount_0_requested: collect.amount_0_requested,
+                amount_1_requested: collect.amount_1_requested,
+            })
+        } else {
+            return Err(anyhow::anyhow!(
+                "Unknown instruction discriminator {:?}",
+                discriminator
+            ));
+        };
+        Ok(instruction)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedAccount {
+    pub role: AccountRole,
+    pub pubkey: Pubkey,
+}
+
+/// A swap-io-clmm instruction with its arguments and every account labelled by role.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    pub program_id: Pubkey,
+    pub instruction: ClmmInstruction,
+    pub accounts: Vec<DecodedAccount>,
+}
+
+impl DecodedInstruction {
+    /// Decode an instruction of the deployment at `program_id`, `swap_io_clmm::id()` or
+    /// the `PoolManager::program_id` of its pool. Other programs are rejected.
+    pub fn decode(program_id: &Pubkey, instruction: &Instruction) -> Result<Self> {
+        let accounts: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .map(|meta| meta.pubkey)
+            .collect();
+        Self::decode_parts(
+            program_id,
+            instruction.program_id,
+            &instruction.data,
+            &accounts,
+        )
+    }
+
+    /// Decode an instruction of a transaction message, `account_keys` being the
+    /// message's keys with any lookup table addresses appended.
+    pub fn decode_compiled(
+        program_id: &Pubkey,
+        instruction: &CompiledInstruction,
+        account_keys: &[Pubkey],
+    ) -> Result<Self> {
+        let key = |index: u8| {
+            account_keys
+                .get(index as usize)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Account index {} out of range", index))
+        };
+        let accounts = instruction
+            .accounts
+            .iter()
+            .map(|index| key(*index))
+            .collect::<Result<Vec<_>>>()?;
+        Self::decode_parts(
+            program_id,
+            key(instruction.program_id_index)?,
+            &instruction.data,
+            &accounts,
+        )
+    }
+
+    fn decode_parts(
+        expected_program_id: &Pubkey,
+        program_id: Pubkey,
+        data: &[u8],
+        accounts: &[Pubkey],
+    ) -> Result<Self> {
+        if program_id != *expected_program_id {
+            return Err(anyhow::anyhow!(
+                "Instruction of program {}, not swap-io-clmm at {}",
+                program_id,
+                expected_program_id
+            ));
+        }
+        let instruction = ClmmInstruction::decode(data)?;
+        let roles = instruction.account_roles();
+        if accounts.len() < roles.len() {
+            return Err(anyhow::anyhow!(
+                "{} expects at least {} accounts, got {}",
+                instruction.name(),
+                roles.len(),
+                accounts.len()
+            ));
+        }
+        let mut decoded: Vec<DecodedAccount> = roles
+            .iter()
+            .zip(accounts)
+            .map(|(role, pubkey)| DecodedAccount {
+                role: *role,
+                pubkey: *pubkey,
+            })
+            .collect();
+        let bitmap_extension = decoded
+            .iter()
+            .find(|account| account.role == AccountRole::PoolState)
+            .map(|pool_state| {
+                Pubkey::find_program_address(
+                    &[
+                        POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+                        pool_state.pubkey.as_ref(),
+                    ],
+                    &program_id,
+                )
+                .0
+            });
+        for pubkey in &accounts[roles.len()..] {
+            let role = if Some(*pubkey) == bitmap_extension {
+                AccountRole::TickArrayBitmapExtension
+            } else if matches!(
+                instruction,
+                ClmmInstruction::Swap(_) | ClmmInstruction::SwapV2(_)
+            ) {
+                AccountRole::TickArray
+            } else {
+                AccountRole::Remaining
+            };
+            decoded.push(DecodedAccount {
+                role,
+                pubkey: *pubkey,
+            });
+        }
+        Ok(DecodedInstruction {
+            program_id,
+            instruction,
+            accounts: decoded,
+        })
+    }
+
+    /// First account with `role`.
+    pub fn account(&self, role: AccountRole) -> Option<Pubkey> {
+        self.accounts
+            .iter()
+            .find(|account| account.role == role)
+            .map(|account| account.pubkey)
+    }
+
+    /// All accounts with `role`, in instruction order.
+    pub fn accounts_with_role(&self, role: AccountRole) -> Vec<Pubkey> {
+        self.accounts
+            .iter()
+            .filter(|account| account.role == role)
+            .map(|account| account.pubkey)
+            .collect()
+    }
+
+    pub fn tick_arrays(&self) -> Vec<Pubkey> {
+        self.accounts_with_role(AccountRole::TickArray)
+    }
+}
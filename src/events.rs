@@ -0,0 +1,110 @@
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::Result;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm::states::{
+    CollectPersonalFeeEvent, CollectProtocolFeeEvent, ConfigChangeEvent,
+    CreatePersonalPositionEvent, DecreaseLiquidityEvent, IncreaseLiquidityEvent,
+    LiquidityCalculateEvent, LiquidityChangeEvent, PoolCreatedEvent, SwapEvent,
+    UpdateRewardInfosEvent,
+};
+
+const PROGRAM_LOG_DATA: &str = "Program data: ";
+
+/// An event emitted by the CLMM program with `emit!`.
+#[derive(Debug)]
+pub enum ClmmEvent {
+    ConfigChange(ConfigChangeEvent),
+    PoolCreated(PoolCreatedEvent),
+    CollectProtocolFee(CollectProtocolFeeEvent),
+    Swap(SwapEvent),
+    LiquidityChange(LiquidityChangeEvent),
+    CreatePersonalPosition(CreatePersonalPositionEvent),
+    IncreaseLiquidity(IncreaseLiquidityEvent),
+    DecreaseLiquidity(DecreaseLiquidityEvent),
+    LiquidityCalculate(LiquidityCalculateEvent),
+    CollectPersonalFee(CollectPersonalFeeEvent),
+    UpdateRewardInfos(UpdateRewardInfosEvent),
+}
+
+fn deserialize_event<T: AnchorDeserialize>(mut data: &[u8]) -> Result<T> {
+    T::deserialize(&mut data).map_err(Into::into)
+}
+
+impl ClmmEvent {
+    /// Decode event data, discriminator included. Returns `None` for a discriminator
+    /// that is not a CLMM event.
+    pub fn decode(data: &[u8]) -> Result<Option<Self>> {
+        if data.len() < 8 {
+            return Err(anyhow::anyhow!("Event data shorter than a discriminator"));
+        }
+        let (discriminator, data) = data.split_at(8);
+        let event = if discriminator == SwapEvent::DISCRIMINATOR {
+            ClmmEvent::Swap(deserialize_event(data)?)
+        } else if discriminator == LiquidityChangeEvent::DISCRIMINATOR {
+            ClmmEvent::LiquidityChange(deserialize_event(data)?)
+        } else if discriminator == CreatePersonalPositionEvent::DISCRIMINATOR {
+            ClmmEvent::CreatePersonalPosition(deserialize_event(data)?)
+        } else if discriminator == IncreaseLiquidityEvent::DISCRIMINATOR {
+            ClmmEvent::IncreaseLiquidity(deserialize_event(data)?)
+        } else if discriminator == DecreaseLiquidityEvent::DISCRIMINATOR {
+            ClmmEvent::DecreaseLiquidity(deserialize_event(data)?)
+        } else if discriminator == LiquidityCalculateEvent::DISCRIMINATOR {
+            ClmmEvent::LiquidityCalculate(deserialize_event(data)?)
+        } else if discriminator == CollectPersonalFeeEvent::DISCRIMINATOR {
+            ClmmEvent::CollectPersonalFee(deserialize_event(data)?)
+        } else if discriminator == UpdateRewardInfosEvent::DISCRIMINATOR {
+            ClmmEvent::UpdateRewardInfos(deserialize_event(data)?)
+        } else if discriminator == PoolCreatedEvent::DISCRIMINATOR {
+            ClmmEvent::PoolCreated(deserialize_event(data)?)
+        } else if discriminator == CollectProtocolFeeEvent::DISCRIMINATOR {
+            ClmmEvent::CollectProtocolFee(deserialize_event(data)?)
+        } else if discriminator == ConfigChangeEvent::DISCRIMINATOR {
+            ClmmEvent::ConfigChange(deserialize_event(data)?)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(event))
+    }
+
+    /// Events emitted by `program_id` in a transaction's log messages, in order.
+    /// Data logged by other programs, including ones the CLMM program invokes, is skipped.
+    pub fn parse_logs<S: AsRef<str>>(program_id: &Pubkey, logs: &[S]) -> Result<Vec<Self>> {
+        let program_id = program_id.to_string();
+        let mut invoke_stack: Vec<&str> = vec![];
+        let mut events = vec![];
+        for log in logs {
+            let log = log.as_ref();
+            if let Some(data) = log.strip_prefix(PROGRAM_LOG_DATA) {
+                if invoke_stack.last() != Some(&program_id.as_str()) {
+                    continue;
+                }
+                if let Some(event) = Self::decode(&STANDARD.decode(data)?)? {
+                    events.push(event);
+                }
+            } else if let Some(rest) = log.strip_prefix("Program ") {
+                // "Program <id> invoke [n]", "Program <id> success", "Program <id> failed: ..."
+                let mut parts = rest.split_whitespace();
+                let (Some(id), Some(status)) = (parts.next(), parts.next()) else {
+                    continue;
+                };
+                if status == "invoke" {
+                    invoke_stack.push(id);
+                } else if status == "success" || status.starts_with("failed") {
+                    invoke_stack.pop();
+                }
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Amounts of an executed swap as `(amount_in, amount_out)`, as the event reports them.
+/// Transfer fees are in `transfer_fee_0`/`transfer_fee_1`.
+pub fn swap_event_amounts(event: &SwapEvent) -> (u64, u64) {
+    if event.zero_for_one {
+        (event.amount_0, event.amount_1)
+    } else {
+        (event.amount_1, event.amount_0)
+    }
+}
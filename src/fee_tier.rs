@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use anchor_lang::Discriminator;
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use swap_io_clmm::states::{AMM_CONFIG_SEED, AmmConfig};
+
+use crate::{pool::PoolManager, registry::PoolRegistry, utils::deserialize_anchor_account};
+
+pub fn amm_config_address(program_id: &Pubkey, index: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[AMM_CONFIG_SEED.as_bytes(), &index.to_be_bytes()],
+        program_id,
+    )
+    .0
+}
+
+/// Tick spacing and fee rates of an `AmmConfig`. Rates are in hundredths of a bip (10^-6),
+/// the protocol rate a share of the trade fee, the fund rate of what the protocol leaves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeTier {
+    #[serde(with = "crate::utils::serde_pubkey")]
+    pub amm_config: Pubkey,
+    pub index: u16,
+    pub tick_spacing: u16,
+    pub trade_fee_rate: u32,
+    pub protocol_fee_rate: u32,
+    pub fund_fee_rate: u32,
+}
+
+impl FeeTier {
+    pub fn new(amm_config_key: Pubkey, amm_config: &AmmConfig) -> Self {
+        FeeTier {
+            amm_config: amm_config_key,
+            index: amm_config.index,
+            tick_spacing: amm_config.tick_spacing,
+            trade_fee_rate: amm_config.trade_fee_rate,
+            protocol_fee_rate: amm_config.protocol_fee_rate,
+            fund_fee_rate: amm_config.fund_fee_rate,
+        }
+    }
+
+    pub fn trade_fee_pct(&self) -> Decimal {
+        Decimal::new(self.trade_fee_rate as i64, 6)
+    }
+}
+
+/// A fee tier whose rates or tick spacing changed between two loads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeTierChange {
+    pub old: FeeTier,
+    pub new: FeeTier,
+}
+
+/// getProgramAccounts filters matching `AmmConfig` accounts: their size and discriminator.
+pub fn amm_config_filters() -> Vec<RpcFilterType> {
+    vec![
+        RpcFilterType::DataSize(AmmConfig::LEN as u64),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &AmmConfig::DISCRIMINATOR)),
+    ]
+}
+
+/// Every `AmmConfig` of the program, by increasing index, whichever indexes the admin
+/// created. One getProgramAccounts call, which the RPC node has to allow.
+pub fn fetch_fee_tiers(rpc_client: &RpcClient, program_id: &Pubkey) -> Result<Vec<FeeTier>> {
+    let accounts = rpc_client.get_program_accounts_with_config(
+        program_id,
+        RpcProgramAccountsConfig {
+            filters: Some(amm_config_filters()),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        },
+    )?;
+    fee_tiers_from_accounts(
+        program_id,
+        accounts
+            .into_iter()
+            .map(|(key, account)| (key, Some(account))),
+    )
+}
+
+/// Decode loaded `AmmConfig` accounts, skipping missing ones, by increasing index. Fails
+/// on an account that is not the config its index says it is.
+pub fn fee_tiers_from_accounts(
+    program_id: &Pubkey,
+    accounts: impl IntoIterator<Item = (Pubkey, Option<Account>)>,
+) -> Result<Vec<FeeTier>> {
+    let mut fee_tiers = vec![];
+    for (key, account) in accounts {
+        let Some(account) = account else {
+            continue;
+        };
+        let amm_config = deserialize_anchor_account::<AmmConfig>(&account)?;
+        if amm_config_address(program_id, amm_config.index) != key {
+            return Err(anyhow::anyhow!(
+                "Account {} is not AmmConfig {}",
+                key,
+                amm_config.index
+            ));
+        }
+        fee_tiers.push(FeeTier::new(key, &amm_config));
+    }
+    fee_tiers.sort_by_key(|fee_tier| fee_tier.index);
+    Ok(fee_tiers)
+}
+
+/// Fee tiers present in both lists whose parameters differ, e.g. after an admin
+/// `update_amm_config`.
+pub fn fee_tier_changes(old: &[FeeTier], new: &[FeeTier]) -> Vec<FeeTierChange> {
+    new.iter()
+        .filter_map(|new| {
+            old.iter()
+                .find(|old| old.amm_config == new.amm_config && *old != new)
+                .map(|old| FeeTierChange {
+                    old: *old,
+                    new: *new,
+                })
+        })
+        .collect()
+}
+
+impl PoolManager {
+    /// Fee tier of the loaded `AmmConfig`.
+    pub fn fee_tier(&self) -> Result<FeeTier> {
+        let amm_config = self
+            .amm_config
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("AmmConfig not initialized"))?;
+        Ok(FeeTier::new(self.pool_state.amm_config, amm_config))
+    }
+
+    /// Fail unless `amm_config` is the config the pool state points to.
+    pub fn check_amm_config(&self, amm_config: &AmmConfig) -> Result<()> {
+        let key = amm_config_address(&self.program_id, amm_config.index);
+        if key != self.pool_state.amm_config {
+            return Err(anyhow::anyhow!(
+                "AmmConfig {} loaded for pool {}, which uses {}",
+                key,
+                self.pool_key,
+                self.pool_state.amm_config
+            ));
+        }
+        if amm_config.tick_spacing != self.pool_state.tick_spacing {
+            return Err(anyhow::anyhow!(
+                "AmmConfig tick spacing {} differs from the pool's {}",
+                amm_config.tick_spacing,
+                self.pool_state.tick_spacing
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl PoolRegistry {
+    /// Loaded pools by the `AmmConfig` they use.
+    pub fn pools_by_amm_config(&self) -> HashMap<Pubkey, Vec<Pubkey>> {
+        let mut pools: HashMap<Pubkey, Vec<Pubkey>> = HashMap::new();
+        for pool_manager in self.pools() {
+            pools
+                .entry(pool_manager.pool_state.amm_config)
+                .or_default()
+                .push(pool_manager.pool_key);
+        }
+        pools
+    }
+}
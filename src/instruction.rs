@@ -1,6 +1,9 @@
 // swap_io_clmm_sdk/src/instruction.rs
 use crate::pool::PoolManager;
+use anchor_lang::InstructionData;
 use anyhow::Result;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
@@ -9,6 +12,10 @@ use solana_sdk::{
 pub struct InstructionBuilder;
 
 impl InstructionBuilder {
+    /// Swap accounts without the payer, tick arrays included, for routers that build
+    /// their own instruction around them: the data is left empty, see
+    /// `build_swap_v2_instruction` for a complete one. Fails if swaps are disabled on the
+    /// pool.
     pub fn build_swap_instruction(
         pool_manager: &PoolManager,
         source_mint: Pubkey,
@@ -16,6 +23,27 @@ impl InstructionBuilder {
         source_token_account: Pubkey,
         destination_token_account: Pubkey,
     ) -> Result<Instruction> {
+        Self::build_swap_instruction_at(
+            pool_manager,
+            source_mint,
+            destination_mint,
+            source_token_account,
+            destination_token_account,
+            None,
+        )
+    }
+
+    /// `build_swap_instruction`, also failing if at `unix_timestamp` the pool is not
+    /// open yet.
+    pub fn build_swap_instruction_at(
+        pool_manager: &PoolManager,
+        source_mint: Pubkey,
+        destination_mint: Pubkey,
+        source_token_account: Pubkey,
+        destination_token_account: Pubkey,
+        unix_timestamp: Option<i64>,
+    ) -> Result<Instruction> {
+        pool_manager.check_swappable(unix_timestamp)?;
         let zero_for_one: bool = source_mint == pool_manager.pool_state.token_mint_0
             && destination_mint == pool_manager.pool_state.token_mint_1;
 
@@ -81,4 +109,123 @@ impl InstructionBuilder {
             data: vec![],
         })
     }
+
+    /// Complete `swap_v2` instruction, signed by `payer`, ready to be sent on its own.
+    pub fn build_swap_v2_instruction(
+        pool_manager: &PoolManager,
+        payer: Pubkey,
+        source_mint: Pubkey,
+        destination_mint: Pubkey,
+        source_token_account: Pubkey,
+        destination_token_account: Pubkey,
+        amount: u64,
+        other_amount_threshold: u64,
+        sqrt_price_limit_x64: Option<u128>,
+        is_base_input: bool,
+    ) -> Result<Instruction> {
+        Self::build_swap_v2_instruction_at(
+            pool_manager,
+            payer,
+            source_mint,
+            destination_mint,
+            source_token_account,
+            destination_token_account,
+            amount,
+            other_amount_threshold,
+            sqrt_price_limit_x64,
+            is_base_input,
+            None,
+        )
+    }
+
+    /// `build_swap_v2_instruction`, also failing if at `unix_timestamp` the pool is not
+    /// open yet.
+    pub fn build_swap_v2_instruction_at(
+        pool_manager: &PoolManager,
+        payer: Pubkey,
+        source_mint: Pubkey,
+        destination_mint: Pubkey,
+        source_token_account: Pubkey,
+        destination_token_account: Pubkey,
+        amount: u64,
+        other_amount_threshold: u64,
+        sqrt_price_limit_x64: Option<u128>,
+        is_base_input: bool,
+        unix_timestamp: Option<i64>,
+    ) -> Result<Instruction> {
+        let mut instruction = Self::build_swap_instruction_at(
+            pool_manager,
+            source_mint,
+            destination_mint,
+            source_token_account,
+            destination_token_account,
+            unix_timestamp,
+        )?;
+        //payer
+        instruction
+            .accounts
+            .insert(0, AccountMeta::new_readonly(payer, true));
+        instruction.data = swap_io_clmm::instruction::SwapV2 {
+            amount,
+            other_amount_threshold,
+            sqrt_price_limit_x64: sqrt_price_limit_x64.unwrap_or(0),
+            is_base_input,
+        }
+        .data();
+        Ok(instruction)
+    }
+}
+
+/// Account meta of an `EncodedInstruction`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodedAccountMeta {
+    #[serde(with = "crate::utils::serde_pubkey")]
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// Instruction with base58 keys and base64 data, for handing to non-Rust clients as json.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodedInstruction {
+    #[serde(with = "crate::utils::serde_pubkey")]
+    pub program_id: Pubkey,
+    pub accounts: Vec<EncodedAccountMeta>,
+    pub data: String,
+}
+
+impl EncodedInstruction {
+    pub fn to_instruction(&self) -> Result<Instruction> {
+        Ok(Instruction {
+            program_id: self.program_id,
+            accounts: self
+                .accounts
+                .iter()
+                .map(|meta| AccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: STANDARD.decode(&self.data)?,
+        })
+    }
+}
+
+impl From<&Instruction> for EncodedInstruction {
+    fn from(instruction: &Instruction) -> Self {
+        EncodedInstruction {
+            program_id: instruction.program_id,
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|meta| EncodedAccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: STANDARD.encode(&instruction.data),
+        }
+    }
 }
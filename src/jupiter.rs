@@ -0,0 +1,182 @@
+use std::sync::atomic::Ordering;
+
+use anyhow::Result;
+use jupiter_amm_interface::{
+    AccountMap, Amm, AmmContext, ClockRef, KeyedAccount, Quote as JupiterQuote, QuoteParams, Swap,
+    SwapAndAccountMetas, SwapMode, SwapParams,
+};
+use solana_sdk::{account::Account, instruction::AccountMeta, pubkey::Pubkey};
+use swap_io_clmm::states::PoolState;
+
+use crate::{
+    instruction::InstructionBuilder,
+    pool::PoolManager,
+    quote::{QuoteCalculator, QuoteConfig},
+    utils::deserialize_anchor_account,
+};
+
+/// `jupiter_amm_interface::Amm` over a single pool.
+#[derive(Clone)]
+pub struct SwapIoClmmAmm {
+    pub pool_manager: PoolManager,
+    clock_ref: ClockRef,
+}
+
+impl SwapIoClmmAmm {
+    fn get_account<'a>(account_map: &'a AccountMap, key: &Pubkey) -> Result<&'a Account> {
+        account_map
+            .get(key)
+            .ok_or_else(|| anyhow::anyhow!("Account {} not found in account map", key))
+    }
+
+    fn get_tick_array_accounts(account_map: &AccountMap, keys: &[Pubkey]) -> Vec<Account> {
+        // the array holding the current tick may not be initialized, it is simply skipped
+        keys.iter()
+            .filter_map(|key| account_map.get(key).cloned())
+            .collect()
+    }
+
+    // zero until jupiter updates the clock
+    fn unix_timestamp(&self) -> Option<i64> {
+        Some(self.clock_ref.unix_timestamp.load(Ordering::Relaxed))
+            .filter(|unix_timestamp| *unix_timestamp > 0)
+    }
+}
+
+impl Amm for SwapIoClmmAmm {
+    fn from_keyed_account(keyed_account: &KeyedAccount, amm_context: &AmmContext) -> Result<Self> {
+        let pool_manager = PoolManager::new(
+            amm_context.clock_ref.epoch.load(Ordering::Relaxed),
+            keyed_account.key,
+            keyed_account.account.owner,
+            &keyed_account.account,
+        )?;
+        Ok(SwapIoClmmAmm {
+            pool_manager,
+            clock_ref: amm_context.clock_ref.clone(),
+        })
+    }
+
+    fn label(&self) -> String {
+        "Swap.io CLMM".to_string()
+    }
+
+    fn program_id(&self) -> Pubkey {
+        self.pool_manager.program_id
+    }
+
+    fn key(&self) -> Pubkey {
+        self.pool_manager.pool_key
+    }
+
+    fn get_reserve_mints(&self) -> Vec<Pubkey> {
+        self.pool_manager.get_reserve_mints()
+    }
+
+    fn get_accounts_to_update(&self) -> Vec<Pubkey> {
+        let pool_state = &self.pool_manager.pool_state;
+        let mut keys = vec![
+            self.pool_manager.pool_key,
+            pool_state.amm_config,
+            pool_state.token_mint_0,
+            pool_state.token_mint_1,
+            self.pool_manager.tick_array_bitmap_extension(),
+        ];
+        keys.extend(self.pool_manager.up_tick_array_keys.iter());
+        keys.extend(self.pool_manager.down_tick_array_keys.iter());
+        keys
+    }
+
+    fn update(&mut self, account_map: &AccountMap) -> Result<()> {
+        if let Some(pool_state_account) = account_map.get(&self.pool_manager.pool_key) {
+            self.pool_manager.pool_state =
+                deserialize_anchor_account::<PoolState>(pool_state_account)?;
+        }
+        let pool_state = &self.pool_manager.pool_state;
+        let accounts = vec![
+            Self::get_account(account_map, &pool_state.amm_config)?,
+            Self::get_account(account_map, &pool_state.token_mint_0)?,
+            Self::get_account(account_map, &pool_state.token_mint_1)?,
+            Self::get_account(
+                account_map,
+                &self.pool_manager.tick_array_bitmap_extension(),
+            )?,
+        ];
+        let up_ticks =
+            Self::get_tick_array_accounts(account_map, &self.pool_manager.up_tick_array_keys);
+        let down_ticks =
+            Self::get_tick_array_accounts(account_map, &self.pool_manager.down_tick_array_keys);
+        self.pool_manager.update(accounts, up_ticks, down_ticks)?;
+        self.pool_manager
+            .set_epoch(self.clock_ref.epoch.load(Ordering::Relaxed))?;
+        // the next round fetches the arrays around the price we just loaded
+        self.pool_manager.refresh_tick_array_keys();
+        Ok(())
+    }
+
+    fn quote(&self, quote_params: &QuoteParams) -> Result<JupiterQuote> {
+        let config = QuoteConfig {
+            epoch: Some(self.clock_ref.epoch.load(Ordering::Relaxed)),
+            open_time_check: self.unix_timestamp(),
+            ui_amount_timestamp: self.unix_timestamp(),
+            ..QuoteConfig::default()
+        };
+        let quote = QuoteCalculator::calculate_quote_with_config(
+            quote_params.input_mint,
+            quote_params.output_mint,
+            quote_params.swap_mode == SwapMode::ExactIn,
+            quote_params.amount,
+            &self.pool_manager,
+            &config,
+        )?;
+        Ok(JupiterQuote {
+            min_in_amount: quote.min_in_amount,
+            min_out_amount: quote.min_out_amount,
+            in_amount: quote.in_amount,
+            out_amount: quote.out_amount,
+            fee_amount: quote.fee_amount,
+            fee_mint: quote.fee_mint,
+            fee_pct: quote.fee_pct,
+            ..JupiterQuote::default()
+        })
+    }
+
+    fn get_swap_and_account_metas(&self, swap_params: &SwapParams) -> Result<SwapAndAccountMetas> {
+        let instruction = InstructionBuilder::build_swap_instruction_at(
+            &self.pool_manager,
+            swap_params.source_mint,
+            swap_params.destination_mint,
+            swap_params.source_token_account,
+            swap_params.destination_token_account,
+            self.unix_timestamp(),
+        )?;
+        let mut account_metas = vec![
+            //clmm_program
+            AccountMeta::new_readonly(self.pool_manager.program_id, false),
+            //payer
+            AccountMeta::new_readonly(swap_params.token_transfer_authority, true),
+        ];
+        account_metas.extend(instruction.accounts);
+        Ok(SwapAndAccountMetas {
+            swap: Swap::RaydiumClmmV2,
+            account_metas,
+        })
+    }
+
+    fn clone_amm(&self) -> Box<dyn Amm + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn supports_exact_out(&self) -> bool {
+        true
+    }
+
+    fn get_accounts_len(&self) -> usize {
+        // program, payer and the 13 fixed swap accounts, plus the tick arrays in either direction
+        15 + self
+            .pool_manager
+            .up_tick_array_keys
+            .len()
+            .max(self.pool_manager.down_tick_array_keys.len())
+    }
+}
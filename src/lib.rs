@@ -1,6 +1,19 @@
+pub mod decoder;
+pub mod events;
+pub mod fee_tier;
 pub mod instruction;
+#[cfg(feature = "jupiter")]
+pub mod jupiter;
+pub mod liquidity;
+pub mod lookup_table;
+pub mod mint;
+pub mod oracle;
 pub mod pool;
 pub mod quote;
+pub mod registry;
+pub mod reserves;
+pub mod snapshot;
+pub mod transaction;
 pub mod utils;
 
 pub fn add(left: u64, right: u64) -> u64 {
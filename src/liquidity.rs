@@ -0,0 +1,172 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use swap_io_clmm::{
+    libraries::{liquidity_math, tick_math},
+    states::TickArrayState,
+};
+
+use crate::{pool::PoolManager, utils::sqrt_price_x64_to_decimal_price};
+
+/// Liquidity active between two ticks, prices in mint 1 per mint 0 adjusted for decimals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiquidityRange {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub price_lower: Decimal,
+    pub price_upper: Decimal,
+}
+
+/// Amounts the pool pays out moving the price from the current one to `tick`: mint 0
+/// for levels above the current price, mint 1 for levels below. Raw token amounts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthLevel {
+    pub tick: i32,
+    pub price: Decimal,
+    pub cumulative_amount_0: u128,
+    pub cumulative_amount_1: u128,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiquidityDistribution {
+    // ascending and contiguous over the loaded tick arrays
+    pub ranges: Vec<LiquidityRange>,
+    // ascending by tick, one level per range boundary
+    pub depth: Vec<DepthLevel>,
+}
+
+impl PoolManager {
+    /// Active liquidity and depth over the window covered by the loaded tick arrays.
+    /// Nothing is known beyond the window, it ends where the outermost arrays end.
+    pub fn get_liquidity_distribution(&self) -> Result<LiquidityDistribution> {
+        let pool_state = &self.pool_state;
+        let tick_count = TickArrayState::tick_count(pool_state.tick_spacing);
+        let mut tick_arrays: Vec<&TickArrayState> = self
+            .up_tick_arrays
+            .iter()
+            .chain(self.down_tick_arrays.iter())
+            .collect();
+        tick_arrays.sort_by_key(|tick_array| tick_array.start_tick_index);
+        tick_arrays.dedup_by_key(|tick_array| tick_array.start_tick_index);
+        let (Some(first), Some(last)) = (tick_arrays.first(), tick_arrays.last()) else {
+            return Err(anyhow::anyhow!("No tick arrays loaded"));
+        };
+        let window_lower = first.start_tick_index.max(tick_math::MIN_TICK);
+        let window_upper = (last.start_tick_index + tick_count).min(tick_math::MAX_TICK);
+        let tick_current = pool_state
+            .tick_current
+            .clamp(window_lower, window_upper - 1);
+
+        // range boundaries with the liquidity_net crossed at each of them
+        let mut boundaries: Vec<(i32, i128)> = vec![(window_lower, 0)];
+        for tick_array in &tick_arrays {
+            for tick_state in tick_array.ticks.iter() {
+                if tick_state.is_initialized()
+                    && tick_state.tick > window_lower
+                    && tick_state.tick < window_upper
+                {
+                    boundaries.push((tick_state.tick, tick_state.liquidity_net));
+                }
+            }
+        }
+        boundaries.push((window_upper, 0));
+
+        // liquidity of the range starting at each boundary, walking out from the current one
+        let current = boundaries
+            .iter()
+            .rposition(|(tick, _)| *tick <= tick_current)
+            .unwrap_or(0);
+        let mut liquidity = vec![0u128; boundaries.len() - 1];
+        liquidity[current] = pool_state.liquidity;
+        for index in current + 1..liquidity.len() {
+            liquidity[index] = liquidity_math::add_delta(liquidity[index - 1], boundaries[index].1)
+                .map_err(|_| anyhow::anyhow!("Liquidity underflow at {}", boundaries[index].0))?;
+        }
+        for index in (0..current).rev() {
+            liquidity[index] =
+                liquidity_math::add_delta(liquidity[index + 1], -boundaries[index + 1].1).map_err(
+                    |_| anyhow::anyhow!("Liquidity underflow at {}", boundaries[index + 1].0),
+                )?;
+        }
+
+        let sqrt_price_at = |tick: i32| -> Result<u128> {
+            tick_math::get_sqrt_price_at_tick(tick)
+                .map_err(|_| anyhow::anyhow!("Tick {} out of range", tick))
+        };
+        let price_at = |sqrt_price_x64: u128| {
+            sqrt_price_x64_to_decimal_price(
+                sqrt_price_x64,
+                pool_state.mint_decimals_0,
+                pool_state.mint_decimals_1,
+            )
+        };
+
+        let mut ranges = Vec::with_capacity(liquidity.len());
+        for (index, liquidity) in liquidity.iter().enumerate() {
+            let (tick_lower, tick_upper) = (boundaries[index].0, boundaries[index + 1].0);
+            ranges.push(LiquidityRange {
+                tick_lower,
+                tick_upper,
+                liquidity: *liquidity,
+                price_lower: price_at(sqrt_price_at(tick_lower)?)?,
+                price_upper: price_at(sqrt_price_at(tick_upper)?)?,
+            });
+        }
+
+        // depth accumulates outwards from the current price, which sits inside ranges[current]
+        let sqrt_price_current = pool_state.sqrt_price_x64;
+        let mut above = vec![];
+        let mut amount_0: u128 = 0;
+        let mut sqrt_price_from = sqrt_price_current;
+        for range in &ranges[current..] {
+            let sqrt_price_upper = sqrt_price_at(range.tick_upper)?;
+            if sqrt_price_upper > sqrt_price_from {
+                amount_0 += liquidity_math::get_delta_amount_0_unsigned(
+                    sqrt_price_from,
+                    sqrt_price_upper,
+                    range.liquidity,
+                    false,
+                )
+                .map_err(|_| anyhow::anyhow!("Depth overflow at {}", range.tick_upper))?
+                    as u128;
+                sqrt_price_from = sqrt_price_upper;
+            }
+            above.push(DepthLevel {
+                tick: range.tick_upper,
+                price: range.price_upper,
+                cumulative_amount_0: amount_0,
+                cumulative_amount_1: 0,
+            });
+        }
+        let mut below = vec![];
+        let mut amount_1: u128 = 0;
+        let mut sqrt_price_from = sqrt_price_current;
+        for range in ranges[..=current].iter().rev() {
+            let sqrt_price_lower = sqrt_price_at(range.tick_lower)?;
+            if sqrt_price_lower < sqrt_price_from {
+                amount_1 += liquidity_math::get_delta_amount_1_unsigned(
+                    sqrt_price_lower,
+                    sqrt_price_from,
+                    range.liquidity,
+                    false,
+                )
+                .map_err(|_| anyhow::anyhow!("Depth overflow at {}", range.tick_lower))?
+                    as u128;
+                sqrt_price_from = sqrt_price_lower;
+            }
+            below.push(DepthLevel {
+                tick: range.tick_lower,
+                price: range.price_lower,
+                cumulative_amount_0: 0,
+                cumulative_amount_1: amount_1,
+            });
+        }
+        below.reverse();
+        below.extend(above);
+
+        Ok(LiquidityDistribution {
+            ranges,
+            depth: below,
+        })
+    }
+}
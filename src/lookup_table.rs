@@ -0,0 +1,60 @@
+use solana_address_lookup_table_program::instruction::{create_lookup_table, extend_lookup_table};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::pool::PoolManager;
+
+// keeps an extend instruction, with its signatures, inside a single transaction
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 30;
+
+/// Accounts every swap on the pool references, whatever the direction and price.
+/// Tick arrays change with the price and are left out.
+pub fn pool_lookup_table_addresses(pool_manager: &PoolManager) -> Vec<Pubkey> {
+    let pool_state = &pool_manager.pool_state;
+    vec![
+        pool_manager.program_id,
+        pool_manager.pool_key,
+        pool_state.amm_config,
+        pool_state.token_vault_0,
+        pool_state.token_vault_1,
+        pool_state.observation_key,
+        pool_manager.tick_array_bitmap_extension(),
+        pool_state.token_mint_0,
+        pool_state.token_mint_1,
+        spl_token::id(),
+        spl_token_2022::id(),
+        spl_memo::id(),
+    ]
+}
+
+/// Extend instructions adding `addresses` to `lookup_table`, split so each fits a transaction.
+pub fn extend_lookup_table_instructions(
+    lookup_table: Pubkey,
+    authority: Pubkey,
+    payer: Pubkey,
+    addresses: &[Pubkey],
+) -> Vec<Instruction> {
+    addresses
+        .chunks(MAX_ADDRESSES_PER_EXTEND)
+        .map(|chunk| extend_lookup_table(lookup_table, authority, Some(payer), chunk.to_vec()))
+        .collect()
+}
+
+/// Instructions creating a lookup table for the pool and filling it with
+/// `pool_lookup_table_addresses`, along with the table address. `recent_slot`
+/// must be a recent finalized slot, it seeds the table address.
+pub fn create_pool_lookup_table_instructions(
+    pool_manager: &PoolManager,
+    authority: Pubkey,
+    payer: Pubkey,
+    recent_slot: u64,
+) -> (Pubkey, Vec<Instruction>) {
+    let (create_instruction, lookup_table) = create_lookup_table(authority, payer, recent_slot);
+    let mut instructions = vec![create_instruction];
+    instructions.extend(extend_lookup_table_instructions(
+        lookup_table,
+        authority,
+        payer,
+        &pool_lookup_table_addresses(pool_manager),
+    ));
+    (lookup_table, instructions)
+}
@@ -0,0 +1,193 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use spl_token_2022::{
+    extension::{
+        BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+        default_account_state::DefaultAccountState,
+        interest_bearing_mint::InterestBearingConfig,
+        permanent_delegate::PermanentDelegate,
+        transfer_fee::{TransferFee, TransferFeeConfig},
+        transfer_hook::TransferHook,
+    },
+    state::{AccountState, Mint},
+};
+
+use crate::pool::PoolManager;
+
+// mint extensions the CLMM program accepts in a pool, a mint with any other one is
+// refused, transfer hooks included
+pub const CLMM_SUPPORTED_EXTENSIONS: [ExtensionType; 4] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+    ExtensionType::InterestBearingConfig,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFeeSchedule {
+    // first epoch the fee applies to
+    pub epoch: u64,
+    pub transfer_fee_basis_points: u16,
+    pub maximum_fee: u64,
+}
+
+impl From<&TransferFee> for TransferFeeSchedule {
+    fn from(transfer_fee: &TransferFee) -> Self {
+        TransferFeeSchedule {
+            epoch: u64::from(transfer_fee.epoch),
+            transfer_fee_basis_points: u16::from(transfer_fee.transfer_fee_basis_points),
+            maximum_fee: u64::from(transfer_fee.maximum_fee),
+        }
+    }
+}
+
+/// What a mint's extensions imply for swapping it, at a given epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintCapabilities {
+    pub decimals: u8,
+    // fee charged on transfers at the epoch analysed
+    pub transfer_fee: Option<TransferFeeSchedule>,
+    // fee taking over at a later epoch
+    pub scheduled_transfer_fee: Option<TransferFeeSchedule>,
+    pub transfer_hook_program: Option<Pubkey>,
+    pub non_transferable: bool,
+    pub permanent_delegate: Option<Pubkey>,
+    // new token accounts start frozen
+    pub default_frozen: bool,
+    pub confidential_transfers: bool,
+    // current rate, in basis points per year
+    pub interest_rate_bps: Option<i16>,
+    pub extensions: Vec<ExtensionType>,
+    pub clmm_supported: bool,
+}
+
+impl MintCapabilities {
+    /// Analyse a mint from its account data, Token or Token-2022.
+    pub fn from_mint_data(data: &[u8], epoch: u64) -> Result<Self> {
+        let mint_state = StateWithExtensions::<Mint>::unpack(data)?;
+        let extensions = mint_state.get_extension_types()?;
+
+        let (transfer_fee, scheduled_transfer_fee) = match mint_state
+            .get_extension::<TransferFeeConfig>()
+        {
+            Ok(transfer_fee_config) => {
+                let newer = &transfer_fee_config.newer_transfer_fee;
+                let scheduled =
+                    (u64::from(newer.epoch) > epoch).then(|| TransferFeeSchedule::from(newer));
+                let current = TransferFeeSchedule::from(transfer_fee_config.get_epoch_fee(epoch));
+                (Some(current), scheduled)
+            }
+            Err(_) => (None, None),
+        };
+        let transfer_hook_program = mint_state
+            .get_extension::<TransferHook>()
+            .ok()
+            .and_then(|transfer_hook| Option::<Pubkey>::from(transfer_hook.program_id));
+        let permanent_delegate = mint_state
+            .get_extension::<PermanentDelegate>()
+            .ok()
+            .and_then(|permanent_delegate| Option::<Pubkey>::from(permanent_delegate.delegate));
+        let default_frozen = mint_state
+            .get_extension::<DefaultAccountState>()
+            .map(|default_state| default_state.state == AccountState::Frozen as u8)
+            .unwrap_or(false);
+        let interest_rate_bps = mint_state
+            .get_extension::<InterestBearingConfig>()
+            .ok()
+            .map(|config| i16::from(config.current_rate));
+
+        Ok(MintCapabilities {
+            decimals: mint_state.base.decimals,
+            transfer_fee,
+            scheduled_transfer_fee,
+            transfer_hook_program,
+            non_transferable: extensions.contains(&ExtensionType::NonTransferable),
+            permanent_delegate,
+            default_frozen,
+            confidential_transfers: extensions.contains(&ExtensionType::ConfidentialTransferMint),
+            interest_rate_bps,
+            clmm_supported: extensions
+                .iter()
+                .all(|extension| CLMM_SUPPORTED_EXTENSIONS.contains(extension)),
+            extensions,
+        })
+    }
+
+    /// Whether a transfer fee applies now or is scheduled to.
+    pub fn has_transfer_fee(&self) -> bool {
+        [self.transfer_fee, self.scheduled_transfer_fee]
+            .iter()
+            .flatten()
+            .any(|fee| fee.transfer_fee_basis_points > 0)
+    }
+
+    /// Extensions letting a third party move, block or intercept the holder's tokens.
+    pub fn has_risky_extensions(&self) -> bool {
+        self.transfer_hook_program.is_some()
+            || self.non_transferable
+            || self.permanent_delegate.is_some()
+            || self.default_frozen
+            || self.confidential_transfers
+    }
+}
+
+/// Amount as displayed to users: divided by 10^decimals, with the interest accrued at
+/// `unix_timestamp` for interest-bearing mints.
+pub fn amount_to_ui_amount(mint_data: &[u8], amount: u64, unix_timestamp: i64) -> Result<Decimal> {
+    let mint_state = StateWithExtensions::<Mint>::unpack(mint_data)?;
+    let decimals = mint_state.base.decimals;
+    match mint_state.get_extension::<InterestBearingConfig>() {
+        Ok(config) => {
+            let ui_amount = config
+                .amount_to_ui_amount(amount, decimals, unix_timestamp)
+                .ok_or_else(|| anyhow::anyhow!("Interest on {} overflows", amount))?;
+            Ok(Decimal::from_str(&ui_amount)?)
+        }
+        Err(_) => Decimal::try_from_i128_with_scale(amount as i128, decimals as u32)
+            .map_err(|_| anyhow::anyhow!("{} decimals not supported", decimals)),
+    }
+}
+
+/// Raw amount of a UI amount, the inverse of `amount_to_ui_amount`. Digits beyond the
+/// mint decimals are truncated.
+pub fn ui_amount_to_amount(
+    mint_data: &[u8],
+    ui_amount: Decimal,
+    unix_timestamp: i64,
+) -> Result<u64> {
+    let mint_state = StateWithExtensions::<Mint>::unpack(mint_data)?;
+    let decimals = mint_state.base.decimals;
+    match mint_state.get_extension::<InterestBearingConfig>() {
+        Ok(config) => Ok(config.try_ui_amount_into_amount(
+            &ui_amount.to_string(),
+            decimals,
+            unix_timestamp,
+        )?),
+        Err(_) => {
+            let multiplier = 10u64
+                .checked_pow(decimals as u32)
+                .map(Decimal::from)
+                .ok_or_else(|| anyhow::anyhow!("{} decimals not supported", decimals))?;
+            ui_amount
+                .checked_mul(multiplier)
+                .and_then(|amount| u64::try_from(amount.trunc()).ok())
+                .ok_or_else(|| anyhow::anyhow!("UI amount {} out of range", ui_amount))
+        }
+    }
+}
+
+impl PoolManager {
+    /// Capabilities of mint 0 and mint 1 at the manager's epoch.
+    pub fn mint_capabilities(&self) -> Result<(MintCapabilities, MintCapabilities)> {
+        let analyse = |mint_data: &Option<Vec<u8>>| -> Result<MintCapabilities> {
+            let mint_data = mint_data
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Mint data not loaded"))?;
+            MintCapabilities::from_mint_data(mint_data, self.epoch)
+        };
+        Ok((analyse(&self.mint0_data)?, analyse(&self.mint1_data)?))
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use swap_io_clmm::states::{OBSERVATION_NUM, Observation, ObservationState, PoolState};
+
+use crate::{
+    pool::PoolManager,
+    utils::{deserialize_anchor_account, tick_to_decimal_price},
+};
+
+/// Time-weighted average over `[start_timestamp, end_timestamp]`, the part of the
+/// requested window the observations cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Twap {
+    pub start_timestamp: u64,
+    pub end_timestamp: u64,
+    // rounded towards negative infinity
+    pub average_tick: i32,
+    // price at average_tick, mint 1 per mint 0 adjusted for decimals
+    pub price: Decimal,
+    pub requested_seconds: u64,
+    pub covered_seconds: u64,
+}
+
+impl Twap {
+    /// Share of the requested window covered, 1 when observations reach back far enough.
+    pub fn coverage(&self) -> Decimal {
+        Decimal::from(self.covered_seconds) / Decimal::from(self.requested_seconds)
+    }
+
+    pub fn is_fully_covered(&self) -> bool {
+        self.covered_seconds == self.requested_seconds
+    }
+}
+
+pub fn deserialize_observation_state(account: &Account) -> Result<ObservationState> {
+    deserialize_anchor_account::<ObservationState>(account)
+}
+
+pub fn fetch_observation_state(
+    rpc_client: &RpcClient,
+    observation_key: &Pubkey,
+) -> Result<ObservationState> {
+    deserialize_observation_state(&rpc_client.get_account(observation_key)?)
+}
+
+/// Recorded observations from the oldest to the latest. Slots of the ring buffer
+/// never written to are left out.
+pub fn observations(observation_state: &ObservationState) -> Vec<Observation> {
+    let latest = observation_state.observation_index as usize;
+    (1..=OBSERVATION_NUM)
+        .map(|offset| observation_state.observations[(latest + offset) % OBSERVATION_NUM])
+        .filter(|observation| observation.block_timestamp != 0)
+        .collect()
+}
+
+// tick cumulative at `timestamp`, interpolated between the observations around it and
+// carried on at `tick_current` after the latest one
+fn tick_cumulative_at(observations: &[Observation], tick_current: i32, timestamp: u64) -> i128 {
+    let after = observations
+        .iter()
+        .position(|observation| observation.block_timestamp as u64 > timestamp);
+    match after {
+        Some(after) => {
+            // the first observation is never after a timestamp clamped to it
+            let (before, after) = (&observations[after - 1], &observations[after]);
+            let elapsed = (timestamp - before.block_timestamp as u64) as i128;
+            let span = (after.block_timestamp - before.block_timestamp) as i128;
+            before.tick_cumulative as i128
+                + (after.tick_cumulative as i128 - before.tick_cumulative as i128) * elapsed / span
+        }
+        None => {
+            let latest = observations.last().unwrap();
+            latest.tick_cumulative as i128
+                + tick_current as i128 * (timestamp - latest.block_timestamp as u64) as i128
+        }
+    }
+}
+
+/// Average tick and price over the `window_seconds` before `now`, from the cumulative
+/// ticks of the observations. Time since the latest observation counts at the pool's
+/// current tick. A window reaching before the oldest observation is cut short, see
+/// `Twap::coverage`.
+pub fn twap(
+    observation_state: &ObservationState,
+    pool_state: &PoolState,
+    now: u64,
+    window_seconds: u64,
+) -> Result<Twap> {
+    if window_seconds == 0 {
+        return Err(anyhow::anyhow!("TWAP window must not be empty"));
+    }
+    let observations = observations(observation_state);
+    let (Some(oldest), Some(latest)) = (observations.first(), observations.last()) else {
+        return Err(anyhow::anyhow!("No observations recorded"));
+    };
+    if now < latest.block_timestamp as u64 {
+        return Err(anyhow::anyhow!(
+            "Time {} is before the latest observation at {}",
+            now,
+            latest.block_timestamp
+        ));
+    }
+    let start_timestamp = now
+        .saturating_sub(window_seconds)
+        .max(oldest.block_timestamp as u64);
+    let covered_seconds = now - start_timestamp;
+    if covered_seconds == 0 {
+        return Err(anyhow::anyhow!("Observations cover none of the window"));
+    }
+
+    let tick_delta = tick_cumulative_at(&observations, pool_state.tick_current, now)
+        - tick_cumulative_at(&observations, pool_state.tick_current, start_timestamp);
+    let average_tick = tick_delta.div_euclid(covered_seconds as i128) as i32;
+    Ok(Twap {
+        start_timestamp,
+        end_timestamp: now,
+        average_tick,
+        price: tick_to_decimal_price(
+            average_tick,
+            pool_state.mint_decimals_0,
+            pool_state.mint_decimals_1,
+        )?,
+        requested_seconds: window_seconds,
+        covered_seconds,
+    })
+}
+
+impl PoolManager {
+    /// TWAP of the pool, see `oracle::twap`. `observation_state` must be the pool's.
+    pub fn twap(
+        &self,
+        observation_state: &ObservationState,
+        now: u64,
+        window_seconds: u64,
+    ) -> Result<Twap> {
+        if observation_state.pool_id != self.pool_key {
+            return Err(anyhow::anyhow!(
+                "Observations of pool {} given for pool {}",
+                observation_state.pool_id,
+                self.pool_key
+            ));
+        }
+        twap(observation_state, &self.pool_state, now, window_seconds)
+    }
+}
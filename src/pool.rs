@@ -1,17 +1,83 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 use anyhow::Result;
-use solana_sdk::{account::Account, pubkey::Pubkey};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    account::{Account, from_account},
+    clock::Clock,
+    pubkey::Pubkey,
+    sysvar,
+};
+use spl_token_2022::{
+    extension::{BaseStateWithExtensions, StateWithExtensions, transfer_fee::TransferFeeConfig},
+    state::Mint,
+};
 use swap_io_clmm::{
     libraries::{U1024, check_current_tick_array_is_initialized, tick_array_bit_map},
     states::{
-        AmmConfig, POOL_TICK_ARRAY_BITMAP_SEED, PoolState, TickArrayBitmapExtension, TickArrayState,
+        AmmConfig, POOL_TICK_ARRAY_BITMAP_SEED, PoolState, PoolStatusBitIndex, SwapEvent,
+        TickArrayBitmapExtension, TickArrayState,
     },
 };
 
 use crate::utils::deserialize_anchor_account;
 pub const NEIGHBORHOOD_SIZE: u8 = 5;
 
+// emitted when an epoch change switches a mint to its other transfer fee schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFeeChange {
+    pub mint: Pubkey,
+    pub old_epoch: u64,
+    pub new_epoch: u64,
+    pub old_transfer_fee_basis_points: u16,
+    pub old_maximum_fee: u64,
+    pub new_transfer_fee_basis_points: u16,
+    pub new_maximum_fee: u64,
+}
+
+// pool price state carried by a swap event, as last applied through apply_swap_event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayedSwap {
+    pub slot: u64,
+    pub sqrt_price_x64: u128,
+    pub liquidity: u128,
+    pub tick: i32,
+}
+
+// a loaded pool state that disagrees with the swap events replayed before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayMismatch {
+    pub slot: u64,
+    pub expected: ReplayedSwap,
+    pub sqrt_price_x64: u128,
+    pub liquidity: u128,
+    pub tick: i32,
+}
+
+// operations the pool admin left enabled, from the PoolState status bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStatus {
+    // opening positions and increasing liquidity
+    pub open_position: bool,
+    pub decrease_liquidity: bool,
+    pub collect_fee: bool,
+    pub collect_reward: bool,
+    pub swap: bool,
+}
+
+impl PoolStatus {
+    pub fn from_pool_state(pool_state: &PoolState) -> Self {
+        PoolStatus {
+            open_position: pool_state
+                .get_status_by_bit(PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity),
+            decrease_liquidity: pool_state.get_status_by_bit(PoolStatusBitIndex::DecreaseLiquidity),
+            collect_fee: pool_state.get_status_by_bit(PoolStatusBitIndex::CollectFee),
+            collect_reward: pool_state.get_status_by_bit(PoolStatusBitIndex::CollectReward),
+            swap: pool_state.get_status_by_bit(PoolStatusBitIndex::Swap),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct PoolManager {
     pub epoch: u64,
@@ -24,8 +90,18 @@ pub struct PoolManager {
     pub tickarray_bitmap_extension: Option<TickArrayBitmapExtension>,
     pub mint0_data: Option<Vec<u8>>,
     pub mint1_data: Option<Vec<u8>>,
+    // token program owning each mint, loaded with its data
+    pub mint0_owner: Option<Pubkey>,
+    pub mint1_owner: Option<Pubkey>,
+    // token_vault_0 and token_vault_1 balances, only tracked once loaded
+    pub vault_amounts: Option<(u64, u64)>,
     pub up_tick_array_keys: Vec<Pubkey>,
     pub down_tick_array_keys: Vec<Pubkey>,
+    // context slot of the rpc response each cached account was loaded from
+    pub account_slots: HashMap<Pubkey, u64>,
+    pub replayed_swap: Option<ReplayedSwap>,
+    // a replayed swap moved the price out of the loaded tick arrays, they need a reload
+    pub tick_arrays_stale: bool,
 }
 
 impl PoolManager {
@@ -47,22 +123,213 @@ impl PoolManager {
             down_tick_arrays: VecDeque::new(),
             mint0_data: None,
             mint1_data: None,
+            mint0_owner: None,
+            mint1_owner: None,
+            vault_amounts: None,
             up_tick_array_keys: vec![],
             down_tick_array_keys: vec![],
+            account_slots: HashMap::new(),
+            replayed_swap: None,
+            tick_arrays_stale: false,
         };
 
+        pool_manager.refresh_tick_array_keys();
+        Ok(pool_manager)
+    }
+
+    /// Load a pool and everything needed to quote it over rpc.
+    pub fn fetch(rpc_client: &RpcClient, pool_key: Pubkey) -> Result<Self> {
+        let response =
+            rpc_client.get_account_with_commitment(&pool_key, rpc_client.commitment())?;
+        let pool_state_account = response
+            .value
+            .ok_or_else(|| anyhow::anyhow!("Pool {} not found", pool_key))?;
+        let epoch = rpc_client.get_epoch_info()?.epoch;
+        let mut pool_manager = Self::new_with_slot(
+            epoch,
+            pool_key,
+            pool_state_account.owner,
+            &pool_state_account,
+            response.context.slot,
+        )?;
+        pool_manager.refresh(rpc_client)?;
+        Ok(pool_manager)
+    }
+
+    /// Reload the pool state, all dependent accounts and the clock in a single rpc call,
+    /// fetching once more if the price moved to different tick arrays in the meantime.
+    /// The epoch follows the clock, so transfer fees switch schedules on their own.
+    pub fn refresh(&mut self, rpc_client: &RpcClient) -> Result<()> {
+        if !self.refresh_once(rpc_client)? {
+            self.refresh_once(rpc_client)?;
+        }
+        Ok(())
+    }
+
+    fn refresh_once(&mut self, rpc_client: &RpcClient) -> Result<bool> {
+        let mut keys = vec![
+            self.pool_key,
+            self.pool_state.amm_config,
+            self.pool_state.token_mint_0,
+            self.pool_state.token_mint_1,
+            self.tick_array_bitmap_extension(),
+        ];
+        keys.extend(self.up_tick_array_keys.iter());
+        keys.extend(self.down_tick_array_keys.iter());
+        // transfer fees follow the epoch, read at the same slot as the mints
+        keys.push(sysvar::clock::id());
+        // vaults are only kept up to date once loaded
+        if self.vault_amounts.is_some() {
+            keys.push(self.pool_state.token_vault_0);
+            keys.push(self.pool_state.token_vault_1);
+        }
+        let response =
+            rpc_client.get_multiple_accounts_with_commitment(&keys, rpc_client.commitment())?;
+        let slot = response.context.slot;
+        let accounts = response.value;
+
+        let pool_state_account = accounts[0]
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Pool {} not found", self.pool_key))?;
+        let mut account_map = vec![];
+        for (key, account) in keys[1..5].iter().zip(accounts[1..5].iter()) {
+            account_map.push(
+                account
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("Account {} not found", key))?,
+            );
+        }
+        // an uninitialized tick array around the current tick does not exist on chain
+        let up_tick_array_end = 5 + self.up_tick_array_keys.len();
+        let up_ticks = accounts[5..up_tick_array_end]
+            .iter()
+            .flatten()
+            .cloned()
+            .collect();
+        let down_tick_array_end = up_tick_array_end + self.down_tick_array_keys.len();
+        let down_ticks = accounts[up_tick_array_end..down_tick_array_end]
+            .iter()
+            .flatten()
+            .cloned()
+            .collect();
+        self.update_pool_state(pool_state_account, slot)?;
+        self.update_with_slot(slot, account_map, up_ticks, down_ticks)?;
+        let clock_account = accounts[down_tick_array_end]
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Clock sysvar not found"))?;
+        self.update_clock_account(clock_account)?;
+        if let [Some(vault_0), Some(vault_1)] = &accounts[down_tick_array_end + 1..] {
+            self.update_vaults_with_slot(slot, vault_0, vault_1)?;
+        }
+
+        let (up_tick_array_keys, down_tick_array_keys) = (
+            self.up_tick_array_keys.clone(),
+            self.down_tick_array_keys.clone(),
+        );
+        self.refresh_tick_array_keys();
+        Ok(up_tick_array_keys == self.up_tick_array_keys
+            && down_tick_array_keys == self.down_tick_array_keys)
+    }
+
+    /// Recompute which tick arrays to load around the current tick, after the price moved.
+    pub fn refresh_tick_array_keys(&mut self) {
         let (up_tick_array_keys, down_tick_array_keys) =
-            match pool_manager.get_nearest_tick_arrays(NEIGHBORHOOD_SIZE) {
+            match self.get_nearest_tick_arrays(NEIGHBORHOOD_SIZE) {
                 Ok((up_tick_arrays, down_tick_arrays)) => {
                     (up_tick_arrays.to_vec(), down_tick_arrays.to_vec())
                 }
                 Err(_) => (vec![], vec![]),
             };
-        pool_manager.up_tick_array_keys = up_tick_array_keys;
-        pool_manager.down_tick_array_keys = down_tick_array_keys;
+        self.up_tick_array_keys = up_tick_array_keys;
+        self.down_tick_array_keys = down_tick_array_keys;
+    }
+
+    pub fn new_with_slot(
+        epoch: u64,
+        pool_key: Pubkey,
+        program_id: Pubkey,
+        pool_state_account: &Account,
+        slot: u64,
+    ) -> Result<Self> {
+        let mut pool_manager = Self::new(epoch, pool_key, program_id, pool_state_account)?;
+        pool_manager.account_slots.insert(pool_key, slot);
         Ok(pool_manager)
     }
 
+    /// Move the manager to `epoch`, returning the transfer fee schedule switches it caused.
+    pub fn set_epoch(&mut self, epoch: u64) -> Result<Vec<TransferFeeChange>> {
+        let mut changes = vec![];
+        for (mint, mint_data) in [
+            (self.pool_state.token_mint_0, &self.mint0_data),
+            (self.pool_state.token_mint_1, &self.mint1_data),
+        ] {
+            let Some(mint_data) = mint_data else {
+                continue;
+            };
+            let mint_state = StateWithExtensions::<Mint>::unpack(mint_data)?;
+            let Ok(transfer_fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+                continue;
+            };
+            let old_fee = transfer_fee_config.get_epoch_fee(self.epoch);
+            let new_fee = transfer_fee_config.get_epoch_fee(epoch);
+            if old_fee != new_fee {
+                changes.push(TransferFeeChange {
+                    mint,
+                    old_epoch: self.epoch,
+                    new_epoch: epoch,
+                    old_transfer_fee_basis_points: u16::from(old_fee.transfer_fee_basis_points),
+                    old_maximum_fee: u64::from(old_fee.maximum_fee),
+                    new_transfer_fee_basis_points: u16::from(new_fee.transfer_fee_basis_points),
+                    new_maximum_fee: u64::from(new_fee.maximum_fee),
+                });
+            }
+        }
+        self.epoch = epoch;
+        Ok(changes)
+    }
+
+    pub fn update_clock(&mut self, clock: &Clock) -> Result<Vec<TransferFeeChange>> {
+        self.set_epoch(clock.epoch)
+    }
+
+    /// Refresh the epoch from a loaded `SysvarC1ock11111111111111111111111111111111` account.
+    pub fn update_clock_account(
+        &mut self,
+        clock_account: &Account,
+    ) -> Result<Vec<TransferFeeChange>> {
+        let clock = from_account::<Clock, _>(clock_account)
+            .ok_or_else(|| anyhow::anyhow!("Invalid clock sysvar account"))?;
+        self.update_clock(&clock)
+    }
+
+    pub fn status(&self) -> PoolStatus {
+        PoolStatus::from_pool_state(&self.pool_state)
+    }
+
+    /// Fail if the program would refuse a swap: swaps disabled, or at `unix_timestamp`
+    /// the pool not open yet. The open time is not checked without a timestamp.
+    pub fn check_swappable(&self, unix_timestamp: Option<i64>) -> Result<()> {
+        if !self.status().swap {
+            return Err(anyhow::anyhow!(
+                "Swaps are disabled on pool {} (status {:#010b})",
+                self.pool_key,
+                self.pool_state.status
+            ));
+        }
+        if let Some(unix_timestamp) = unix_timestamp {
+            // the program requires the block time to be strictly after open_time
+            if unix_timestamp as i128 <= self.pool_state.open_time as i128 {
+                return Err(anyhow::anyhow!(
+                    "Pool {} opens at {}, not open at {}",
+                    self.pool_key,
+                    self.pool_state.open_time,
+                    unix_timestamp
+                ));
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_reserve_mints(&self) -> Vec<Pubkey> {
         vec![self.pool_state.token_mint_0, self.pool_state.token_mint_1]
     }
@@ -172,6 +439,34 @@ impl PoolManager {
         return Ok((false, next_start_index.unwrap()));
     }
 
+    /// Start indexes of every initialized tick array in the pool bitmap, ascending.
+    pub fn get_initialized_tick_array_start_indexes(&self) -> Result<Vec<i32>> {
+        let current_start_index = TickArrayState::get_array_start_index(
+            self.pool_state.tick_current,
+            self.pool_state.tick_spacing,
+        );
+        let mut start_indexes = vec![];
+        let (is_initialized, _) = check_current_tick_array_is_initialized(
+            U1024(self.pool_state.tick_array_bitmap),
+            self.pool_state.tick_current,
+            self.pool_state.tick_spacing.into(),
+        )?;
+        if is_initialized {
+            start_indexes.push(current_start_index);
+        }
+        for zero_for_one in [true, false] {
+            let mut start_index = current_start_index;
+            while let Some(next_start_index) =
+                self.next_initialized_tick_array_start_index(zero_for_one, start_index)?
+            {
+                start_indexes.push(next_start_index);
+                start_index = next_start_index;
+            }
+        }
+        start_indexes.sort();
+        Ok(start_indexes)
+    }
+
     fn process_direction(&self, is_zero_for_one: bool, neighbor_count: u8) -> Result<Vec<Pubkey>> {
         match self.get_first_initialized_tick_array(is_zero_for_one) {
             Ok((_, start_index)) => {
@@ -209,17 +504,18 @@ impl PoolManager {
         Ok((up_tick_arrays, down_tick_arrays))
     }
 
+    // derived under the pool's program id, `TickArrayState::key` assumes the default one
     pub fn get_up_tick_array_keys(&self) -> Vec<Pubkey> {
         self.up_tick_arrays
             .iter()
-            .map(|tick_array| tick_array.key())
+            .map(|tick_array| self.tick_array(tick_array.start_tick_index))
             .collect()
     }
 
     pub fn get_down_tick_array_keys(&self) -> Vec<Pubkey> {
         self.down_tick_arrays
             .iter()
-            .map(|tick_array| tick_array.key())
+            .map(|tick_array| self.tick_array(tick_array.start_tick_index))
             .collect()
     }
 
@@ -229,14 +525,202 @@ impl PoolManager {
         up_ticks: Vec<Account>,
         down_ticks: Vec<Account>,
     ) -> Result<()> {
-        self.amm_config = Some(deserialize_anchor_account::<AmmConfig>(&account_map[0])?);
+        let amm_config = deserialize_anchor_account::<AmmConfig>(&account_map[0])?;
+        self.check_amm_config(&amm_config)?;
+        self.amm_config = Some(amm_config);
         self.mint0_data = Some(account_map[1].data.clone());
         self.mint1_data = Some(account_map[2].data.clone());
+        self.mint0_owner = Some(account_map[1].owner);
+        self.mint1_owner = Some(account_map[2].owner);
         self.tickarray_bitmap_extension = Some(deserialize_anchor_account::<
             TickArrayBitmapExtension,
         >(&account_map[3])?);
         Self::update_tick_arrays(up_ticks, &mut self.up_tick_arrays)?;
         Self::update_tick_arrays(down_ticks, &mut self.down_tick_arrays)?;
+        self.tick_arrays_stale = false;
+        // accounts refreshed without a context slot must not keep an older one
+        for key in self.get_loaded_account_keys() {
+            if !self.is_updated_separately(&key) {
+                self.account_slots.remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    // the pool state and vaults are not among the accounts `update` loads
+    fn is_updated_separately(&self, key: &Pubkey) -> bool {
+        *key == self.pool_key
+            || *key == self.pool_state.token_vault_0
+            || *key == self.pool_state.token_vault_1
+    }
+
+    pub fn update_with_slot(
+        &mut self,
+        slot: u64,
+        account_map: Vec<&Account>,
+        up_ticks: Vec<Account>,
+        down_ticks: Vec<Account>,
+    ) -> Result<()> {
+        self.update(account_map, up_ticks, down_ticks)?;
+        for key in self.get_loaded_account_keys() {
+            if !self.is_updated_separately(&key) {
+                self.account_slots.insert(key, slot);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn update_pool_state(&mut self, pool_state_account: &Account, slot: u64) -> Result<()> {
+        self.pool_state = deserialize_anchor_account::<PoolState>(pool_state_account)?;
+        self.account_slots.insert(self.pool_key, slot);
+        self.replayed_swap = None;
+        Ok(())
+    }
+
+    /// Load the pool state like `update_pool_state`, and report if it disagrees with
+    /// the swap events replayed up to `slot`, meaning an event was missed or misordered.
+    /// A pool state older than the last replayed event is not compared.
+    pub fn update_pool_state_checked(
+        &mut self,
+        pool_state_account: &Account,
+        slot: u64,
+    ) -> Result<Option<ReplayMismatch>> {
+        let replayed_swap = self.replayed_swap;
+        self.update_pool_state(pool_state_account, slot)?;
+        let Some(expected) = replayed_swap.filter(|replayed_swap| slot >= replayed_swap.slot)
+        else {
+            return Ok(None);
+        };
+        let pool_state = &self.pool_state;
+        if pool_state.sqrt_price_x64 == expected.sqrt_price_x64
+            && pool_state.liquidity == expected.liquidity
+            && pool_state.tick_current == expected.tick
+        {
+            return Ok(None);
+        }
+        Ok(Some(ReplayMismatch {
+            slot,
+            expected,
+            sqrt_price_x64: pool_state.sqrt_price_x64,
+            liquidity: pool_state.liquidity,
+            tick: pool_state.tick_current,
+        }))
+    }
+
+    /// Move the pool to the price, liquidity and tick a swap left it at, without
+    /// refetching. Events older than the loaded pool state are ignored, `false` is
+    /// returned for them. Tick arrays are not reloaded: if the new price needs other
+    /// arrays the manager is marked stale and refuses to quote until the next `update`.
+    pub fn apply_swap_event(&mut self, event: &SwapEvent, slot: u64) -> Result<bool> {
+        if event.pool_state != self.pool_key {
+            return Err(anyhow::anyhow!(
+                "Swap event for pool {} applied to {}",
+                event.pool_state,
+                self.pool_key
+            ));
+        }
+        if self
+            .get_account_slot(&self.pool_key)
+            .is_some_and(|pool_slot| slot < pool_slot)
+        {
+            return Ok(false);
+        }
+        self.pool_state.sqrt_price_x64 = event.sqrt_price_x64;
+        self.pool_state.liquidity = event.liquidity;
+        self.pool_state.tick_current = event.tick;
+        self.account_slots.insert(self.pool_key, slot);
+        self.replayed_swap = Some(ReplayedSwap {
+            slot,
+            sqrt_price_x64: event.sqrt_price_x64,
+            liquidity: event.liquidity,
+            tick: event.tick,
+        });
+
+        let (up_tick_array_keys, down_tick_array_keys) = (
+            self.up_tick_array_keys.clone(),
+            self.down_tick_array_keys.clone(),
+        );
+        self.refresh_tick_array_keys();
+        if up_tick_array_keys != self.up_tick_array_keys
+            || down_tick_array_keys != self.down_tick_array_keys
+        {
+            self.tick_arrays_stale = true;
+        }
+        Ok(true)
+    }
+
+    /// Keys of every account currently cached by the manager, pool state first.
+    pub fn get_loaded_account_keys(&self) -> Vec<Pubkey> {
+        let mut keys = vec![self.pool_key];
+        if self.amm_config.is_some() {
+            keys.push(self.pool_state.amm_config);
+        }
+        if self.mint0_data.is_some() {
+            keys.push(self.pool_state.token_mint_0);
+        }
+        if self.mint1_data.is_some() {
+            keys.push(self.pool_state.token_mint_1);
+        }
+        if self.tickarray_bitmap_extension.is_some() {
+            keys.push(self.tick_array_bitmap_extension());
+        }
+        if self.vault_amounts.is_some() {
+            keys.push(self.pool_state.token_vault_0);
+            keys.push(self.pool_state.token_vault_1);
+        }
+        keys.extend(self.get_up_tick_array_keys());
+        keys.extend(self.get_down_tick_array_keys());
+        keys
+    }
+
+    pub fn get_account_slot(&self, key: &Pubkey) -> Option<u64> {
+        self.account_slots.get(key).copied()
+    }
+
+    /// Returns the (oldest, newest) context slot over all cached accounts,
+    /// or None if any cached account was loaded without a slot.
+    pub fn get_slot_range(&self) -> Option<(u64, u64)> {
+        let mut range: Option<(u64, u64)> = None;
+        for key in self.get_loaded_account_keys() {
+            let slot = self.get_account_slot(&key)?;
+            range = Some(match range {
+                Some((min_slot, max_slot)) => (min_slot.min(slot), max_slot.max(slot)),
+                None => (slot, slot),
+            });
+        }
+        range
+    }
+
+    /// Check that all cached accounts were loaded within `max_slot_spread` slots of each other.
+    pub fn check_slot_consistency(&self, max_slot_spread: u64) -> Result<()> {
+        let (min_slot, max_slot) = self
+            .get_slot_range()
+            .ok_or_else(|| anyhow::anyhow!("Account slots not recorded"))?;
+        if max_slot - min_slot > max_slot_spread {
+            return Err(anyhow::anyhow!(
+                "Inconsistent account slots: {} to {} exceeds spread {}",
+                min_slot,
+                max_slot,
+                max_slot_spread
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that no cached account is more than `max_slot_age` slots behind `current_slot`.
+    pub fn check_slot_staleness(&self, current_slot: u64, max_slot_age: u64) -> Result<()> {
+        let (min_slot, _) = self
+            .get_slot_range()
+            .ok_or_else(|| anyhow::anyhow!("Account slots not recorded"))?;
+        let age = current_slot.saturating_sub(min_slot);
+        if age > max_slot_age {
+            return Err(anyhow::anyhow!(
+                "Stale accounts: oldest slot {} is {} slots behind {}",
+                min_slot,
+                age,
+                current_slot
+            ));
+        }
         Ok(())
     }
 
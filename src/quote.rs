@@ -1,32 +1,111 @@
 use std::collections::VecDeque;
 
 use crate::{
+    mint::amount_to_ui_amount,
     pool::PoolManager,
     utils::{
-        amount_with_slippage, get_out_put_amount_fee_and_remaining_accounts, get_transfer_fee,
-        get_transfer_inverse_fee,
+        amount_with_slippage, decimal_price_to_sqrt_price_x64, get_transfer_fee,
+        get_transfer_inverse_fee, simulate_swap,
     },
 };
 use anyhow::Result;
 use rust_decimal::Decimal;
-use solana_sdk::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{clock::Clock, pubkey::Pubkey};
 use spl_token_2022::{extension::StateWithExtensions, state::Mint};
 use swap_io_clmm::states::TickArrayState;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 pub struct Quote {
     pub min_in_amount: Option<u64>,
     pub min_out_amount: Option<u64>,
     pub in_amount: u64,
     pub out_amount: u64,
     pub fee_amount: u64,
+    #[serde(with = "crate::utils::serde_pubkey")]
     pub fee_mint: Pubkey,
     pub fee_pct: Decimal,
+    // split of fee_amount between liquidity providers, the protocol and the fund
+    pub lp_fee_amount: u64,
+    pub protocol_fee_amount: u64,
+    pub fund_fee_amount: u64,
+    // amounts as displayed, set when the config has a ui_amount_timestamp
+    pub ui_in_amount: Option<Decimal>,
+    pub ui_out_amount: Option<Decimal>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct QuoteConfig {
+    // slot the caller considers current, required by max_slot_age
+    pub current_slot: Option<u64>,
+    // refuse to quote if any account is older than this many slots
+    pub max_slot_age: Option<u64>,
+    // refuse to quote if accounts were loaded more than this many slots apart
+    pub max_slot_spread: Option<u64>,
+    // epoch used for transfer fees, falls back to PoolManager::epoch
+    pub epoch: Option<u64>,
+    // time the pool must be open at, the open time is not checked without it
+    pub open_time_check: Option<i64>,
+    // time interest-bearing amounts are converted at, UI amounts are left out without it
+    pub ui_amount_timestamp: Option<i64>,
+}
+
+impl QuoteConfig {
+    pub fn with_clock(mut self, clock: &Clock) -> Self {
+        self.current_slot = Some(clock.slot);
+        self.epoch = Some(clock.epoch);
+        self.open_time_check = Some(clock.unix_timestamp);
+        self.ui_amount_timestamp = Some(clock.unix_timestamp);
+        self
+    }
+}
+
+/// Swap that moves the pool to a target price, see `QuoteCalculator::calculate_amount_to_price`.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct PriceTargetQuote {
+    pub zero_for_one: bool,
+    #[serde(with = "crate::utils::serde_pubkey")]
+    pub input_mint: Pubkey,
+    #[serde(with = "crate::utils::serde_pubkey")]
+    pub output_mint: Pubkey,
+    // sent by the user, trade fee and input transfer fee included
+    pub in_amount: u64,
+    // received by the user, net of the output transfer fee
+    pub out_amount: u64,
+    // trade fee, in the input mint
+    pub fee_amount: u64,
+    pub sqrt_price_x64: u128,
+    pub tick: i32,
 }
 
 pub struct QuoteCalculator;
 
 impl QuoteCalculator {
+    // refuse to quote on state the config considers stale
+    fn check_pool_manager(pool_manager: &PoolManager, config: &QuoteConfig) -> Result<()> {
+        pool_manager.check_swappable(config.open_time_check)?;
+        if pool_manager.tick_arrays_stale {
+            return Err(anyhow::anyhow!(
+                "Tick arrays around tick {} not loaded, update the pool",
+                pool_manager.pool_state.tick_current
+            ));
+        }
+        if let Some(max_slot_spread) = config.max_slot_spread {
+            pool_manager.check_slot_consistency(max_slot_spread)?;
+        }
+        if let Some(max_slot_age) = config.max_slot_age {
+            let current_slot = config
+                .current_slot
+                .ok_or_else(|| anyhow::anyhow!("current_slot required to check staleness"))?;
+            pool_manager.check_slot_staleness(current_slot, max_slot_age)?;
+        }
+        Ok(())
+    }
+
+    /// Quote at `pool_manager.epoch`, which only moves with `refresh`, `update_clock` and
+    /// `set_epoch`. A manager kept across epochs without those charges the transfer fees
+    /// of the epoch it was loaded in, pass the current clock through
+    /// `calculate_quote_with_config` and `QuoteConfig::with_clock` instead.
     pub fn calculate_quote(
         input_mint: Pubkey,
         output_mint: Pubkey,
@@ -34,6 +113,25 @@ impl QuoteCalculator {
         amount: u64,
         pool_manager: &PoolManager,
     ) -> Result<Quote> {
+        Self::calculate_quote_with_config(
+            input_mint,
+            output_mint,
+            base_in,
+            amount,
+            pool_manager,
+            &QuoteConfig::default(),
+        )
+    }
+
+    pub fn calculate_quote_with_config(
+        input_mint: Pubkey,
+        output_mint: Pubkey,
+        base_in: bool,
+        amount: u64,
+        pool_manager: &PoolManager,
+        config: &QuoteConfig,
+    ) -> Result<Quote> {
+        Self::check_pool_manager(pool_manager, config)?;
         let mint0_data = pool_manager
             .mint0_data
             .as_ref()
@@ -51,23 +149,30 @@ impl QuoteCalculator {
             .tickarray_bitmap_extension
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("TickArrayBitmapExtension not initialized"))?;
+        let epoch = config.epoch.unwrap_or(pool_manager.epoch);
         let zero_for_one = input_mint == pool_manager.pool_state.token_mint_0
             && output_mint == pool_manager.pool_state.token_mint_1;
 
         let mint0_state = StateWithExtensions::<Mint>::unpack(&mint0_data)?;
         let mint1_state = StateWithExtensions::<Mint>::unpack(&mint1_data)?;
-        let transfer_fee = if base_in {
-            if zero_for_one {
-                get_transfer_fee(&mint0_state, pool_manager.epoch, amount)
-            } else {
-                get_transfer_fee(&mint1_state, pool_manager.epoch, amount)
-            }
+        let (input_mint_state, output_mint_state) = if zero_for_one {
+            (&mint0_state, &mint1_state)
+        } else {
+            (&mint1_state, &mint0_state)
+        };
+        let amount_specified = if base_in {
+            // the pool receives the input net of its transfer fee
+            let transfer_fee = get_transfer_fee(input_mint_state, epoch, amount);
+            amount
+                .checked_sub(transfer_fee)
+                .ok_or(anyhow::anyhow!("Amount underflow"))?
         } else {
-            0
+            // the pool has to send enough for `amount` to arrive after the output transfer fee
+            let transfer_fee = get_transfer_inverse_fee(output_mint_state, epoch, amount);
+            amount
+                .checked_add(transfer_fee)
+                .ok_or(anyhow::anyhow!("Amount overflow"))?
         };
-        let amount_specified = amount
-            .checked_sub(transfer_fee)
-            .ok_or(anyhow::anyhow!("Amount underflow"))?;
         // load tick_arrays
         let mut tick_arrays: VecDeque<TickArrayState>;
         if zero_for_one {
@@ -78,30 +183,40 @@ impl QuoteCalculator {
 
         let sqrt_price_limit_x64 = None;
 
+        let state = simulate_swap(
+            amount_specified,
+            sqrt_price_limit_x64,
+            zero_for_one,
+            base_in,
+            amm_config,
+            &pool_manager.pool_state,
+            tickarray_bitmap_extension,
+            &mut tick_arrays,
+        )
+        .map_err(|err| anyhow::anyhow!(err))?;
+        // what leaves the output vault, before the output transfer fee
+        let pool_out_amount = if base_in {
+            state.amount_calculated
+        } else {
+            amount_specified
+        };
+        pool_manager.check_output_reserve(zero_for_one, pool_out_amount)?;
         let (mut other_amount_threshold, fee_amount) =
-            get_out_put_amount_fee_and_remaining_accounts(
-                amount_specified,
-                sqrt_price_limit_x64,
-                zero_for_one,
-                base_in,
-                amm_config,
-                &pool_manager.pool_state,
-                tickarray_bitmap_extension,
-                &mut tick_arrays,
-            )
-            .unwrap();
+            (state.amount_calculated, state.fee_calculated);
         if base_in {
+            // calc mint out amount received after transfer_fee
+            let transfer_fee = get_transfer_fee(output_mint_state, epoch, other_amount_threshold);
+            other_amount_threshold = other_amount_threshold
+                .checked_sub(transfer_fee)
+                .ok_or(anyhow::anyhow!("Amount underflow"))?;
             // calc mint out amount with slippage
             other_amount_threshold = amount_with_slippage(other_amount_threshold, 0.0, false);
         } else {
             // calc max in with slippage
             other_amount_threshold = amount_with_slippage(other_amount_threshold, 0.0, true);
             // calc max in with transfer_fee
-            let transfer_fee = if zero_for_one {
-                get_transfer_inverse_fee(&mint0_state, pool_manager.epoch, other_amount_threshold)
-            } else {
-                get_transfer_inverse_fee(&mint1_state, pool_manager.epoch, other_amount_threshold)
-            };
+            let transfer_fee =
+                get_transfer_inverse_fee(input_mint_state, epoch, other_amount_threshold);
             other_amount_threshold = other_amount_threshold
                 .checked_add(transfer_fee)
                 .ok_or(anyhow::anyhow!("Amount overflow"))?;
@@ -122,13 +237,162 @@ impl QuoteCalculator {
         // pub trade_fee_rate: u32,
         let fee_pct: Decimal = Decimal::new(amm_config.trade_fee_rate as i64, 6);
 
+        let (ui_in_amount, ui_out_amount) = match config.ui_amount_timestamp {
+            Some(unix_timestamp) => {
+                let (input_mint_data, output_mint_data) = if zero_for_one {
+                    (mint0_data, mint1_data)
+                } else {
+                    (mint1_data, mint0_data)
+                };
+                (
+                    Some(amount_to_ui_amount(
+                        input_mint_data,
+                        in_amount,
+                        unix_timestamp,
+                    )?),
+                    Some(amount_to_ui_amount(
+                        output_mint_data,
+                        out_amount,
+                        unix_timestamp,
+                    )?),
+                )
+            }
+            None => (None, None),
+        };
+
         Ok(Quote {
             fee_pct: fee_pct,
             in_amount: in_amount,
             out_amount: out_amount,
             fee_amount: fee_amount,
             fee_mint: input_mint,
+            lp_fee_amount: fee_amount - state.protocol_fee - state.fund_fee,
+            protocol_fee_amount: state.protocol_fee,
+            fund_fee_amount: state.fund_fee,
+            ui_in_amount,
+            ui_out_amount,
             ..Quote::default()
         })
     }
+
+    /// Exact input needed to move the pool price to `price` (mint 1 per mint 0, adjusted
+    /// for decimals), see `calculate_amount_to_sqrt_price`.
+    pub fn calculate_amount_to_price(
+        price: Decimal,
+        pool_manager: &PoolManager,
+        config: &QuoteConfig,
+    ) -> Result<PriceTargetQuote> {
+        let sqrt_price_x64 = decimal_price_to_sqrt_price_x64(
+            price,
+            pool_manager.pool_state.mint_decimals_0,
+            pool_manager.pool_state.mint_decimals_1,
+        )?;
+        Self::calculate_amount_to_sqrt_price(sqrt_price_x64, pool_manager, config)
+    }
+
+    /// Exact input needed to move the pool to `sqrt_price_x64`, with the output received
+    /// and the trade fee paid on the way. A target below the current price sells mint 0,
+    /// above it sells mint 1. Fails if the loaded tick arrays do not reach the target.
+    pub fn calculate_amount_to_sqrt_price(
+        sqrt_price_x64: u128,
+        pool_manager: &PoolManager,
+        config: &QuoteConfig,
+    ) -> Result<PriceTargetQuote> {
+        Self::check_pool_manager(pool_manager, config)?;
+        let pool_state = &pool_manager.pool_state;
+        let zero_for_one = sqrt_price_x64 < pool_state.sqrt_price_x64;
+        let (input_mint, output_mint) = if zero_for_one {
+            (pool_state.token_mint_0, pool_state.token_mint_1)
+        } else {
+            (pool_state.token_mint_1, pool_state.token_mint_0)
+        };
+        if sqrt_price_x64 == pool_state.sqrt_price_x64 {
+            return Ok(PriceTargetQuote {
+                zero_for_one,
+                input_mint,
+                output_mint,
+                sqrt_price_x64,
+                tick: pool_state.tick_current,
+                ..PriceTargetQuote::default()
+            });
+        }
+        let mint0_data = pool_manager
+            .mint0_data
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Mint0Data not initialized"))?;
+        let mint1_data = pool_manager
+            .mint1_data
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Mint1Data not initialized"))?;
+        let amm_config = pool_manager
+            .amm_config
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("AmmConfig not initialized"))?;
+        let tickarray_bitmap_extension = pool_manager
+            .tickarray_bitmap_extension
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("TickArrayBitmapExtension not initialized"))?;
+        let epoch = config.epoch.unwrap_or(pool_manager.epoch);
+
+        let mut tick_arrays = if zero_for_one {
+            pool_manager.up_tick_arrays.clone()
+        } else {
+            pool_manager.down_tick_arrays.clone()
+        };
+        // an input the pool can never absorb, so the swap only stops at the target
+        let unbounded_amount = u64::MAX;
+        let state = simulate_swap(
+            unbounded_amount,
+            Some(sqrt_price_x64),
+            zero_for_one,
+            true,
+            amm_config,
+            pool_state,
+            tickarray_bitmap_extension,
+            &mut tick_arrays,
+        )
+        .map_err(|err| {
+            anyhow::anyhow!(
+                "Loaded liquidity can not reach sqrt price {}: {}",
+                sqrt_price_x64,
+                err
+            )
+        })?;
+        if state.sqrt_price_x64 != sqrt_price_x64 {
+            return Err(anyhow::anyhow!(
+                "Loaded liquidity can not reach sqrt price {}, stopped at {}",
+                sqrt_price_x64,
+                state.sqrt_price_x64
+            ));
+        }
+        pool_manager.check_output_reserve(zero_for_one, state.amount_calculated)?;
+
+        let mint0_state = StateWithExtensions::<Mint>::unpack(mint0_data)?;
+        let mint1_state = StateWithExtensions::<Mint>::unpack(mint1_data)?;
+        let (input_mint_state, output_mint_state) = if zero_for_one {
+            (&mint0_state, &mint1_state)
+        } else {
+            (&mint1_state, &mint0_state)
+        };
+        let pool_in_amount = unbounded_amount - state.amount_specified_remaining;
+        let in_amount = pool_in_amount
+            .checked_add(get_transfer_inverse_fee(
+                input_mint_state,
+                epoch,
+                pool_in_amount,
+            ))
+            .ok_or(anyhow::anyhow!("Amount overflow"))?;
+        let out_amount = state.amount_calculated
+            - get_transfer_fee(output_mint_state, epoch, state.amount_calculated);
+        Ok(PriceTargetQuote {
+            zero_for_one,
+            input_mint,
+            output_mint,
+            in_amount,
+            out_amount,
+            fee_amount: state.fee_calculated,
+            sqrt_price_x64: state.sqrt_price_x64,
+            tick: state.tick,
+        })
+    }
 }
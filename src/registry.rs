@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    pool::PoolManager,
+    quote::{Quote, QuoteCalculator, QuoteConfig},
+    snapshot::PoolSnapshot,
+};
+
+/// A set of loaded pools, looked up by address or by mint pair.
+#[derive(Clone, Default)]
+pub struct PoolRegistry {
+    pools: HashMap<Pubkey, PoolManager>,
+    // newest epoch seen by a fetch or refresh, None for snapshot-only registries
+    epoch: Option<u64>,
+}
+
+impl PoolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every pool in `pool_keys` over rpc.
+    pub fn fetch(rpc_client: &RpcClient, pool_keys: &[Pubkey]) -> Result<Self> {
+        let mut registry = Self::new();
+        for pool_key in pool_keys {
+            let pool_manager = PoolManager::fetch(rpc_client, *pool_key)?;
+            registry.epoch = registry.epoch.max(Some(pool_manager.epoch));
+            registry.insert(pool_manager);
+        }
+        Ok(registry)
+    }
+
+    /// Load every pool from snapshot files, without rpc.
+    pub fn load_snapshots<P: AsRef<std::path::Path>>(paths: &[P]) -> Result<Self> {
+        let mut registry = Self::new();
+        for path in paths {
+            registry.insert(PoolSnapshot::load(path)?.to_pool_manager()?);
+        }
+        Ok(registry)
+    }
+
+    /// Add or replace a pool, returning the previous one.
+    pub fn insert(&mut self, pool_manager: PoolManager) -> Option<PoolManager> {
+        self.pools.insert(pool_manager.pool_key, pool_manager)
+    }
+
+    pub fn remove(&mut self, pool_key: &Pubkey) -> Option<PoolManager> {
+        self.pools.remove(pool_key)
+    }
+
+    pub fn get(&self, pool_key: &Pubkey) -> Option<&PoolManager> {
+        self.pools.get(pool_key)
+    }
+
+    pub fn pool_keys(&self) -> Vec<Pubkey> {
+        self.pools.keys().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+
+    /// The current epoch as of the last fetch or refresh. Pass it as `QuoteConfig::epoch`
+    /// so a pool whose refresh failed still charges the current transfer fees.
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch
+    }
+
+    pub fn pools(&self) -> impl Iterator<Item = &PoolManager> {
+        self.pools.values()
+    }
+
+    /// Pools trading `mint_a` against `mint_b`, in either order.
+    pub fn pools_for_pair(&self, mint_a: Pubkey, mint_b: Pubkey) -> Vec<&PoolManager> {
+        self.pools
+            .values()
+            .filter(|pool_manager| {
+                let mints = pool_manager.get_reserve_mints();
+                mints.contains(&mint_a) && mints.contains(&mint_b) && mint_a != mint_b
+            })
+            .collect()
+    }
+
+    /// Refresh every pool over rpc. A pool that fails keeps its previous state, the
+    /// failures are returned so the caller can decide whether to drop it.
+    pub fn refresh(&mut self, rpc_client: &RpcClient) -> Vec<(Pubkey, anyhow::Error)> {
+        let mut errors = vec![];
+        for (pool_key, pool_manager) in self.pools.iter_mut() {
+            let mut refreshed = pool_manager.clone();
+            match refreshed.refresh(rpc_client) {
+                Ok(()) => {
+                    self.epoch = self.epoch.max(Some(refreshed.epoch));
+                    *pool_manager = refreshed;
+                }
+                Err(err) => errors.push((*pool_key, err)),
+            }
+        }
+        errors
+    }
+
+    /// Quote on `pool_key`, or on the best pool for the pair when it is `None`: the one
+    /// paying out the most for exact-in, or asking the least for exact-out.
+    pub fn quote(
+        &self,
+        pool_key: Option<Pubkey>,
+        input_mint: Pubkey,
+        output_mint: Pubkey,
+        base_in: bool,
+        amount: u64,
+        config: &QuoteConfig,
+    ) -> Result<(Pubkey, Quote)> {
+        let candidates = match pool_key {
+            Some(pool_key) => {
+                let pool_manager = self
+                    .get(&pool_key)
+                    .ok_or_else(|| anyhow::anyhow!("Pool {} not loaded", pool_key))?;
+                let mints = pool_manager.get_reserve_mints();
+                if !mints.contains(&input_mint) || !mints.contains(&output_mint) {
+                    return Err(anyhow::anyhow!(
+                        "Pool {} does not trade {} for {}",
+                        pool_key,
+                        input_mint,
+                        output_mint
+                    ));
+                }
+                vec![pool_manager]
+            }
+            None => self.pools_for_pair(input_mint, output_mint),
+        };
+
+        let mut best: Option<(Pubkey, Quote)> = None;
+        let mut last_error = None;
+        for pool_manager in candidates {
+            let quote = match QuoteCalculator::calculate_quote_with_config(
+                input_mint,
+                output_mint,
+                base_in,
+                amount,
+                pool_manager,
+                config,
+            ) {
+                Ok(quote) => quote,
+                Err(err) => {
+                    last_error = Some(err);
+                    continue;
+                }
+            };
+            let better = match &best {
+                None => true,
+                Some((_, best_quote)) if base_in => quote.out_amount > best_quote.out_amount,
+                Some((_, best_quote)) => quote.in_amount < best_quote.in_amount,
+            };
+            if better {
+                best = Some((pool_manager.pool_key, quote));
+            }
+        }
+        match (best, last_error) {
+            (Some(best), _) => Ok(best),
+            (None, Some(err)) => Err(err),
+            (None, None) => Err(anyhow::anyhow!(
+                "No pool loaded for {} and {}",
+                input_mint,
+                output_mint
+            )),
+        }
+    }
+}
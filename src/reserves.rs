@@ -0,0 +1,165 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use spl_token_2022::{extension::StateWithExtensions, state::Account as TokenAccount};
+
+use crate::{pool::PoolManager, utils::sqrt_price_x64_to_decimal_price};
+
+/// Vault balances of a pool and the unclaimed protocol and fund fees they hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reserves {
+    pub vault_amount_0: u64,
+    pub vault_amount_1: u64,
+    pub protocol_fees_0: u64,
+    pub protocol_fees_1: u64,
+    pub fund_fees_0: u64,
+    pub fund_fees_1: u64,
+}
+
+impl Reserves {
+    /// Mint 0 held for liquidity providers, unclaimed protocol and fund fees excluded.
+    pub fn reserve_0(&self) -> u64 {
+        self.vault_amount_0
+            .saturating_sub(self.protocol_fees_0)
+            .saturating_sub(self.fund_fees_0)
+    }
+
+    /// Mint 1 held for liquidity providers, unclaimed protocol and fund fees excluded.
+    pub fn reserve_1(&self) -> u64 {
+        self.vault_amount_1
+            .saturating_sub(self.protocol_fees_1)
+            .saturating_sub(self.fund_fees_1)
+    }
+
+    /// Value of both reserves in mint 1 at `price` (mint 1 per mint 0, adjusted for
+    /// decimals), in UI units.
+    pub fn tvl(&self, price: Decimal, decimals_0: u8, decimals_1: u8) -> Result<Decimal> {
+        let ui_reserve = |amount: u64, decimals: u8| {
+            Decimal::try_from_i128_with_scale(amount as i128, decimals as u32)
+                .map_err(|err| anyhow::anyhow!("Reserve {} not representable: {}", amount, err))
+        };
+        let reserve_0 = ui_reserve(self.reserve_0(), decimals_0)?;
+        let reserve_1 = ui_reserve(self.reserve_1(), decimals_1)?;
+        reserve_0
+            .checked_mul(price)
+            .and_then(|value_0| value_0.checked_add(reserve_1))
+            .ok_or_else(|| anyhow::anyhow!("TVL overflow at price {}", price))
+    }
+}
+
+/// Balance of a pool vault, checked to be a token account of `mint` owned by the pool.
+pub fn vault_amount(account: &Account, mint: &Pubkey, pool_key: &Pubkey) -> Result<u64> {
+    let vault = StateWithExtensions::<TokenAccount>::unpack(&account.data)?;
+    if vault.base.mint != *mint {
+        return Err(anyhow::anyhow!(
+            "Vault holds mint {}, expected {}",
+            vault.base.mint,
+            mint
+        ));
+    }
+    if vault.base.owner != *pool_key {
+        return Err(anyhow::anyhow!(
+            "Vault owned by {}, not pool {}",
+            vault.base.owner,
+            pool_key
+        ));
+    }
+    Ok(vault.base.amount)
+}
+
+impl PoolManager {
+    /// Load the vault token accounts. Once loaded, `refresh` keeps them up to date and
+    /// quotes are checked against them.
+    pub fn update_vaults(&mut self, vault_0: &Account, vault_1: &Account) -> Result<()> {
+        let pool_state = &self.pool_state;
+        let vault_amount_0 = vault_amount(vault_0, &pool_state.token_mint_0, &self.pool_key)?;
+        let vault_amount_1 = vault_amount(vault_1, &pool_state.token_mint_1, &self.pool_key)?;
+        self.vault_amounts = Some((vault_amount_0, vault_amount_1));
+        self.account_slots.remove(&pool_state.token_vault_0);
+        self.account_slots.remove(&pool_state.token_vault_1);
+        Ok(())
+    }
+
+    pub fn update_vaults_with_slot(
+        &mut self,
+        slot: u64,
+        vault_0: &Account,
+        vault_1: &Account,
+    ) -> Result<()> {
+        self.update_vaults(vault_0, vault_1)?;
+        self.account_slots
+            .insert(self.pool_state.token_vault_0, slot);
+        self.account_slots
+            .insert(self.pool_state.token_vault_1, slot);
+        Ok(())
+    }
+
+    /// Load the vaults over rpc, see `update_vaults`.
+    pub fn fetch_vaults(&mut self, rpc_client: &RpcClient) -> Result<()> {
+        let keys = [self.pool_state.token_vault_0, self.pool_state.token_vault_1];
+        let response =
+            rpc_client.get_multiple_accounts_with_commitment(&keys, rpc_client.commitment())?;
+        let (Some(vault_0), Some(vault_1)) = (&response.value[0], &response.value[1]) else {
+            return Err(anyhow::anyhow!(
+                "Vaults of pool {} not found",
+                self.pool_key
+            ));
+        };
+        self.update_vaults_with_slot(response.context.slot, vault_0, vault_1)
+    }
+
+    /// Reserves from the loaded vaults and the fees the pool state owes.
+    pub fn reserves(&self) -> Result<Reserves> {
+        let (vault_amount_0, vault_amount_1) = self
+            .vault_amounts
+            .ok_or_else(|| anyhow::anyhow!("Vaults not loaded"))?;
+        Ok(Reserves {
+            vault_amount_0,
+            vault_amount_1,
+            protocol_fees_0: self.pool_state.protocol_fees_token_0,
+            protocol_fees_1: self.pool_state.protocol_fees_token_1,
+            fund_fees_0: self.pool_state.fund_fees_token_0,
+            fund_fees_1: self.pool_state.fund_fees_token_1,
+        })
+    }
+
+    /// Withdrawable value of the pool in mint 1 at the pool price, in UI units.
+    pub fn tvl(&self) -> Result<Decimal> {
+        let pool_state = &self.pool_state;
+        let price = sqrt_price_x64_to_decimal_price(
+            pool_state.sqrt_price_x64,
+            pool_state.mint_decimals_0,
+            pool_state.mint_decimals_1,
+        )?;
+        self.reserves()?.tvl(
+            price,
+            pool_state.mint_decimals_0,
+            pool_state.mint_decimals_1,
+        )
+    }
+
+    /// Fail if the pool would have to send more of the output mint than its reserve.
+    /// Passes when the vaults are not loaded.
+    pub fn check_output_reserve(&self, zero_for_one: bool, amount_out: u64) -> Result<()> {
+        let Ok(reserves) = self.reserves() else {
+            return Ok(());
+        };
+        let (reserve, mint) = if zero_for_one {
+            (reserves.reserve_1(), self.pool_state.token_mint_1)
+        } else {
+            (reserves.reserve_0(), self.pool_state.token_mint_0)
+        };
+        if amount_out > reserve {
+            return Err(anyhow::anyhow!(
+                "Swap sends {} of mint {}, pool {} holds {}",
+                amount_out,
+                mint,
+                self.pool_key,
+                reserve
+            ));
+        }
+        Ok(())
+    }
+}
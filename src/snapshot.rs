@@ -0,0 +1,468 @@
+use std::{collections::VecDeque, fs, path::Path};
+
+use anyhow::Result;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm::states::{AmmConfig, PoolState, TickArrayBitmapExtension, TickArrayState};
+
+use crate::{
+    fee_tier::FeeTier,
+    mint::MintCapabilities,
+    pool::PoolManager,
+    utils::{
+        deserialize_anchor_account_data, serialize_anchor_account, serialize_zero_copy_account,
+    },
+};
+
+// binary snapshots start with this tag and the format version of what follows, those
+// written before versioning have neither and use the layout of `SnapshotV0`
+const SNAPSHOT_MAGIC: &[u8; 8] = b"CLMMSNAP";
+const SNAPSHOT_VERSION: u8 = 1;
+
+// pubkeys are written as base58 strings in json and as raw bytes in binary snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SnapshotPubkey(Pubkey);
+
+impl Serialize for SnapshotPubkey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.0.to_string())
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SnapshotPubkey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let key = String::deserialize(deserializer)?;
+            key.parse()
+                .map(SnapshotPubkey)
+                .map_err(serde::de::Error::custom)
+        } else {
+            Pubkey::deserialize(deserializer).map(SnapshotPubkey)
+        }
+    }
+}
+
+// account data is written as base64 in json and as raw bytes in binary snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AccountBytes(Vec<u8>);
+
+impl Serialize for AccountBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(&self.0))
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AccountBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let data = String::deserialize(deserializer)?;
+            STANDARD
+                .decode(data)
+                .map(AccountBytes)
+                .map_err(serde::de::Error::custom)
+        } else {
+            Vec::<u8>::deserialize(deserializer).map(AccountBytes)
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolStateSummary {
+    #[serde(with = "crate::utils::serde_pubkey")]
+    pub amm_config: Pubkey,
+    #[serde(with = "crate::utils::serde_pubkey")]
+    pub token_mint_0: Pubkey,
+    #[serde(with = "crate::utils::serde_pubkey")]
+    pub token_mint_1: Pubkey,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    pub tick_current: i32,
+    pub sqrt_price_x64: u128,
+    pub liquidity: u128,
+    pub status: u8,
+    pub open_time: u64,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+}
+
+impl PoolStateSummary {
+    pub fn new(pool_state: &PoolState) -> Self {
+        PoolStateSummary {
+            amm_config: pool_state.amm_config,
+            token_mint_0: pool_state.token_mint_0,
+            token_mint_1: pool_state.token_mint_1,
+            mint_decimals_0: pool_state.mint_decimals_0,
+            mint_decimals_1: pool_state.mint_decimals_1,
+            tick_spacing: pool_state.tick_spacing,
+            tick_current: pool_state.tick_current,
+            sqrt_price_x64: pool_state.sqrt_price_x64,
+            liquidity: pool_state.liquidity,
+            status: pool_state.status,
+            open_time: pool_state.open_time,
+            protocol_fees_token_0: pool_state.protocol_fees_token_0,
+            protocol_fees_token_1: pool_state.protocol_fees_token_1,
+            fund_fees_token_0: pool_state.fund_fees_token_0,
+            fund_fees_token_1: pool_state.fund_fees_token_1,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MintSummary {
+    pub decimals: u8,
+    // at the snapshot's epoch
+    pub transfer_fee_basis_points: Option<u16>,
+    pub extensions: Vec<String>,
+}
+
+impl MintSummary {
+    pub fn new(mint_data: &[u8], epoch: u64) -> Result<Self> {
+        let capabilities = MintCapabilities::from_mint_data(mint_data, epoch)?;
+        Ok(MintSummary {
+            decimals: capabilities.decimals,
+            transfer_fee_basis_points: capabilities
+                .transfer_fee
+                .map(|transfer_fee| transfer_fee.transfer_fee_basis_points),
+            extensions: capabilities
+                .extensions
+                .iter()
+                .map(|extension| format!("{:?}", extension))
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TickSummary {
+    pub tick: i32,
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+}
+
+// initialized ticks only
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TickArraySummary {
+    pub start_tick_index: i32,
+    pub ticks: Vec<TickSummary>,
+}
+
+impl TickArraySummary {
+    pub fn new(tick_array: &TickArrayState) -> Self {
+        let ticks = tick_array.ticks;
+        TickArraySummary {
+            start_tick_index: tick_array.start_tick_index,
+            ticks: ticks
+                .iter()
+                .filter(|tick_state| tick_state.liquidity_gross != 0)
+                .map(|tick_state| TickSummary {
+                    tick: tick_state.tick,
+                    liquidity_net: tick_state.liquidity_net,
+                    liquidity_gross: tick_state.liquidity_gross,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Decoded fields of the snapshotted accounts, so a json snapshot can be read and
+/// diffed. Informational only: loading a snapshot uses the raw account data.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotSummary {
+    pub pool_state: PoolStateSummary,
+    pub fee_tier: Option<FeeTier>,
+    pub mint_0: Option<MintSummary>,
+    pub mint_1: Option<MintSummary>,
+    pub up_tick_arrays: Vec<TickArraySummary>,
+    pub down_tick_arrays: Vec<TickArraySummary>,
+}
+
+impl SnapshotSummary {
+    /// Never fails, a mint that can not be analysed is left out.
+    pub fn new(pool_manager: &PoolManager) -> Self {
+        let mint = |mint_data: &Option<Vec<u8>>| {
+            mint_data
+                .as_ref()
+                .and_then(|mint_data| MintSummary::new(mint_data, pool_manager.epoch).ok())
+        };
+        let tick_arrays = |tick_arrays: &VecDeque<TickArrayState>| {
+            tick_arrays.iter().map(TickArraySummary::new).collect()
+        };
+        SnapshotSummary {
+            pool_state: PoolStateSummary::new(&pool_manager.pool_state),
+            fee_tier: pool_manager.fee_tier().ok(),
+            mint_0: mint(&pool_manager.mint0_data),
+            mint_1: mint(&pool_manager.mint1_data),
+            up_tick_arrays: tick_arrays(&pool_manager.up_tick_arrays),
+            down_tick_arrays: tick_arrays(&pool_manager.down_tick_arrays),
+        }
+    }
+}
+
+// only json carries the summary, binary snapshots write nothing for it. Loading either
+// rebuilds it from the accounts
+fn serialize_summary<S: Serializer>(
+    summary: &SnapshotSummary,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        summary.serialize(serializer)
+    } else {
+        serializer.serialize_unit()
+    }
+}
+
+/// Everything a `PoolManager` needs to quote, stored as raw account data so it
+/// can be reloaded without rpc, next to a decoded `SnapshotSummary` of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolSnapshot {
+    epoch: u64,
+    pool_key: SnapshotPubkey,
+    program_id: SnapshotPubkey,
+    #[serde(serialize_with = "serialize_summary", skip_deserializing)]
+    summary: SnapshotSummary,
+    pool_state: AccountBytes,
+    amm_config: Option<AccountBytes>,
+    tickarray_bitmap_extension: Option<AccountBytes>,
+    up_tick_arrays: Vec<AccountBytes>,
+    down_tick_arrays: Vec<AccountBytes>,
+    mint0_data: Option<AccountBytes>,
+    mint1_data: Option<AccountBytes>,
+    up_tick_array_keys: Vec<SnapshotPubkey>,
+    down_tick_array_keys: Vec<SnapshotPubkey>,
+    account_slots: Vec<(SnapshotPubkey, u64)>,
+    // added with format version 1, unversioned snapshots load without them
+    vault_amounts: Option<(u64, u64)>,
+    mint0_owner: Option<SnapshotPubkey>,
+    mint1_owner: Option<SnapshotPubkey>,
+}
+
+impl PoolSnapshot {
+    pub fn from_pool_manager(pool_manager: &PoolManager) -> Result<Self> {
+        // a snapshot has no way to record that its tick arrays do not match the price
+        if pool_manager.tick_arrays_stale {
+            return Err(anyhow::anyhow!(
+                "Tick arrays of pool {} are stale, update before taking a snapshot",
+                pool_manager.pool_key
+            ));
+        }
+        let amm_config = match &pool_manager.amm_config {
+            Some(amm_config) => Some(AccountBytes(serialize_anchor_account(amm_config)?)),
+            None => None,
+        };
+        let mut account_slots: Vec<(SnapshotPubkey, u64)> = pool_manager
+            .account_slots
+            .iter()
+            .map(|(key, slot)| (SnapshotPubkey(*key), *slot))
+            .collect();
+        // keep the output stable so identical managers produce identical files
+        account_slots.sort_by_key(|(key, _)| key.0);
+        Ok(PoolSnapshot {
+            epoch: pool_manager.epoch,
+            pool_key: SnapshotPubkey(pool_manager.pool_key),
+            program_id: SnapshotPubkey(pool_manager.program_id),
+            summary: SnapshotSummary::new(pool_manager),
+            pool_state: AccountBytes(serialize_zero_copy_account(&pool_manager.pool_state)),
+            amm_config,
+            tickarray_bitmap_extension: pool_manager
+                .tickarray_bitmap_extension
+                .as_ref()
+                .map(|extension| AccountBytes(serialize_zero_copy_account(extension))),
+            up_tick_arrays: Self::tick_arrays_to_bytes(&pool_manager.up_tick_arrays),
+            down_tick_arrays: Self::tick_arrays_to_bytes(&pool_manager.down_tick_arrays),
+            mint0_data: pool_manager.mint0_data.clone().map(AccountBytes),
+            mint1_data: pool_manager.mint1_data.clone().map(AccountBytes),
+            up_tick_array_keys: Self::keys_to_snapshot(&pool_manager.up_tick_array_keys),
+            down_tick_array_keys: Self::keys_to_snapshot(&pool_manager.down_tick_array_keys),
+            account_slots,
+            vault_amounts: pool_manager.vault_amounts,
+            mint0_owner: pool_manager.mint0_owner.map(SnapshotPubkey),
+            mint1_owner: pool_manager.mint1_owner.map(SnapshotPubkey),
+        })
+    }
+
+    pub fn to_pool_manager(&self) -> Result<PoolManager> {
+        Ok(PoolManager {
+            epoch: self.epoch,
+            pool_key: self.pool_key.0,
+            program_id: self.program_id.0,
+            pool_state: deserialize_anchor_account_data::<PoolState>(&self.pool_state.0)?,
+            amm_config: self
+                .amm_config
+                .as_ref()
+                .map(|data| deserialize_anchor_account_data::<AmmConfig>(&data.0))
+                .transpose()?,
+            up_tick_arrays: Self::tick_arrays_from_bytes(&self.up_tick_arrays)?,
+            down_tick_arrays: Self::tick_arrays_from_bytes(&self.down_tick_arrays)?,
+            tickarray_bitmap_extension: self
+                .tickarray_bitmap_extension
+                .as_ref()
+                .map(|data| deserialize_anchor_account_data::<TickArrayBitmapExtension>(&data.0))
+                .transpose()?,
+            mint0_data: self.mint0_data.as_ref().map(|data| data.0.clone()),
+            mint1_data: self.mint1_data.as_ref().map(|data| data.0.clone()),
+            mint0_owner: self.mint0_owner.map(|owner| owner.0),
+            mint1_owner: self.mint1_owner.map(|owner| owner.0),
+            vault_amounts: self.vault_amounts,
+            up_tick_array_keys: self.up_tick_array_keys.iter().map(|key| key.0).collect(),
+            down_tick_array_keys: self.down_tick_array_keys.iter().map(|key| key.0).collect(),
+            account_slots: self
+                .account_slots
+                .iter()
+                .map(|(key, slot)| (key.0, *slot))
+                .collect(),
+            replayed_swap: None,
+            tick_arrays_stale: false,
+        })
+    }
+
+    pub fn summary(&self) -> &SnapshotSummary {
+        &self.summary
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut data = SNAPSHOT_MAGIC.to_vec();
+        data.push(SNAPSHOT_VERSION);
+        bincode::serialize_into(&mut data, self)?;
+        Ok(data)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let snapshot: Self = match data.strip_prefix(SNAPSHOT_MAGIC.as_slice()) {
+            Some([SNAPSHOT_VERSION, data @ ..]) => bincode::deserialize(data)?,
+            Some([version, ..]) => {
+                return Err(anyhow::anyhow!(
+                    "Snapshot format version {} not supported, {} is the latest",
+                    version,
+                    SNAPSHOT_VERSION
+                ));
+            }
+            Some([]) => return Err(anyhow::anyhow!("Snapshot format version missing")),
+            None => bincode::deserialize::<SnapshotV0>(data)?.into(),
+        };
+        Ok(Self::with_summary(snapshot))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(Into::into)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(Self::with_summary(serde_json::from_str(json)?))
+    }
+
+    // a snapshot whose accounts do not decode keeps an empty summary, loading it fails later
+    fn with_summary(mut snapshot: Self) -> Self {
+        if let Ok(pool_manager) = snapshot.to_pool_manager() {
+            snapshot.summary = SnapshotSummary::new(&pool_manager);
+        }
+        snapshot
+    }
+
+    /// Write the snapshot to `path`, as json if the extension is `.json` and binary otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if Self::is_json_path(path) {
+            fs::write(path, self.to_json()?)?;
+        } else {
+            fs::write(path, self.to_bytes()?)?;
+        }
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path)?;
+        if Self::is_json_path(path) {
+            Self::from_json(std::str::from_utf8(&data)?)
+        } else {
+            Self::from_bytes(&data)
+        }
+    }
+
+    fn is_json_path(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension == "json")
+    }
+
+    fn tick_arrays_to_bytes(tick_arrays: &VecDeque<TickArrayState>) -> Vec<AccountBytes> {
+        tick_arrays
+            .iter()
+            .map(|tick_array| AccountBytes(serialize_zero_copy_account(tick_array)))
+            .collect()
+    }
+
+    fn tick_arrays_from_bytes(tick_arrays: &[AccountBytes]) -> Result<VecDeque<TickArrayState>> {
+        tick_arrays
+            .iter()
+            .map(|data| deserialize_anchor_account_data::<TickArrayState>(&data.0))
+            .collect()
+    }
+
+    fn keys_to_snapshot(keys: &[Pubkey]) -> Vec<SnapshotPubkey> {
+        keys.iter().map(|key| SnapshotPubkey(*key)).collect()
+    }
+}
+
+// binary layout before the format version, without vault amounts and mint owners
+#[derive(Deserialize)]
+struct SnapshotV0 {
+    epoch: u64,
+    pool_key: SnapshotPubkey,
+    program_id: SnapshotPubkey,
+    pool_state: AccountBytes,
+    amm_config: Option<AccountBytes>,
+    tickarray_bitmap_extension: Option<AccountBytes>,
+    up_tick_arrays: Vec<AccountBytes>,
+    down_tick_arrays: Vec<AccountBytes>,
+    mint0_data: Option<AccountBytes>,
+    mint1_data: Option<AccountBytes>,
+    up_tick_array_keys: Vec<SnapshotPubkey>,
+    down_tick_array_keys: Vec<SnapshotPubkey>,
+    account_slots: Vec<(SnapshotPubkey, u64)>,
+}
+
+impl From<SnapshotV0> for PoolSnapshot {
+    fn from(snapshot: SnapshotV0) -> Self {
+        PoolSnapshot {
+            epoch: snapshot.epoch,
+            pool_key: snapshot.pool_key,
+            program_id: snapshot.program_id,
+            summary: SnapshotSummary::default(),
+            pool_state: snapshot.pool_state,
+            amm_config: snapshot.amm_config,
+            tickarray_bitmap_extension: snapshot.tickarray_bitmap_extension,
+            up_tick_arrays: snapshot.up_tick_arrays,
+            down_tick_arrays: snapshot.down_tick_arrays,
+            mint0_data: snapshot.mint0_data,
+            mint1_data: snapshot.mint1_data,
+            up_tick_array_keys: snapshot.up_tick_array_keys,
+            down_tick_array_keys: snapshot.down_tick_array_keys,
+            account_slots: snapshot.account_slots,
+            vault_amounts: None,
+            mint0_owner: None,
+            mint1_owner: None,
+        }
+    }
+}
+
+impl PoolManager {
+    pub fn to_snapshot(&self) -> Result<PoolSnapshot> {
+        PoolSnapshot::from_pool_manager(self)
+    }
+
+    pub fn from_snapshot(snapshot: &PoolSnapshot) -> Result<Self> {
+        snapshot.to_pool_manager()
+    }
+}
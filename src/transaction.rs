@@ -0,0 +1,369 @@
+use anyhow::Result;
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    message::{VersionedMessage, v0},
+    pubkey::Pubkey,
+    signature::Signature,
+    system_instruction,
+    transaction::VersionedTransaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::{create_associated_token_account, create_associated_token_account_idempotent},
+};
+
+use crate::{
+    instruction::InstructionBuilder,
+    pool::PoolManager,
+    quote::Quote,
+    utils::{amount_with_slippage, simulate_swap},
+};
+
+// Rough estimates with a margin, not measured against the program: a swap that stays
+// in the current tick array, plus each additional tick array crossed.
+// Set `SwapTransactionOptions::compute_unit_limit` from a simulation where it matters.
+pub const SWAP_BASE_COMPUTE_UNITS: u32 = 80_000;
+pub const COMPUTE_UNITS_PER_TICK_ARRAY: u32 = 30_000;
+pub const CREATE_ATA_COMPUTE_UNITS: u32 = 30_000;
+// transfer, sync_native, close_account and memo together
+pub const WRAP_SOL_COMPUTE_UNITS: u32 = 15_000;
+pub const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+
+#[derive(Debug, Clone)]
+pub struct SwapTransactionOptions {
+    pub slippage_bps: u16,
+    // overrides the estimate from the tick arrays crossed
+    pub compute_unit_limit: Option<u32>,
+    pub compute_unit_price_micro_lamports: Option<u64>,
+    // create the wallet's output ata if it does not exist yet
+    pub create_output_account: bool,
+    // wrap native SOL into the input ata and unwrap SOL received or left over
+    pub wrap_and_unwrap_sol: bool,
+    // the wallet already has a wSOL ata: it is then neither created nor closed, so
+    // wSOL it held before the swap stays wrapped
+    pub wsol_account_exists: bool,
+    pub memo: Option<String>,
+    // token programs of the input and output mint, the owners of the loaded mints when unset
+    pub input_token_program: Option<Pubkey>,
+    pub output_token_program: Option<Pubkey>,
+    // refuse to build before the pool's open time when set
+    pub unix_timestamp: Option<i64>,
+}
+
+impl Default for SwapTransactionOptions {
+    fn default() -> Self {
+        SwapTransactionOptions {
+            slippage_bps: 50,
+            compute_unit_limit: None,
+            compute_unit_price_micro_lamports: None,
+            create_output_account: true,
+            wrap_and_unwrap_sol: true,
+            wsol_account_exists: false,
+            memo: None,
+            input_token_program: None,
+            output_token_program: None,
+            unix_timestamp: None,
+        }
+    }
+}
+
+/// Assembles everything around a `swap_v2` instruction: compute budget, ata creation,
+/// SOL wrapping and a memo, for a quote from `QuoteCalculator`.
+pub struct SwapTransactionBuilder<'a> {
+    pool_manager: &'a PoolManager,
+    wallet: Pubkey,
+    input_mint: Pubkey,
+    output_mint: Pubkey,
+    base_in: bool,
+    quote: Quote,
+    options: SwapTransactionOptions,
+}
+
+impl<'a> SwapTransactionBuilder<'a> {
+    pub fn new(
+        pool_manager: &'a PoolManager,
+        wallet: Pubkey,
+        input_mint: Pubkey,
+        output_mint: Pubkey,
+        base_in: bool,
+        quote: Quote,
+    ) -> Self {
+        SwapTransactionBuilder {
+            pool_manager,
+            wallet,
+            input_mint,
+            output_mint,
+            base_in,
+            quote,
+            options: SwapTransactionOptions::default(),
+        }
+    }
+
+    pub fn options(mut self, options: SwapTransactionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn zero_for_one(&self) -> bool {
+        self.input_mint == self.pool_manager.pool_state.token_mint_0
+    }
+
+    // the mint's owner: a Token-2022 mint is one whether or not it has extensions
+    fn token_program(&self, mint: Pubkey, token_program: Option<Pubkey>) -> Result<Pubkey> {
+        if let Some(token_program) = token_program {
+            return Ok(token_program);
+        }
+        let mint_owner = if mint == self.pool_manager.pool_state.token_mint_0 {
+            self.pool_manager.mint0_owner
+        } else {
+            self.pool_manager.mint1_owner
+        };
+        mint_owner.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Token program of mint {} unknown, load the mint or set it in the options",
+                mint
+            )
+        })
+    }
+
+    pub fn input_token_program(&self) -> Result<Pubkey> {
+        self.token_program(self.input_mint, self.options.input_token_program)
+    }
+
+    pub fn output_token_program(&self) -> Result<Pubkey> {
+        self.token_program(self.output_mint, self.options.output_token_program)
+    }
+
+    pub fn input_token_account(&self) -> Result<Pubkey> {
+        Ok(get_associated_token_address_with_program_id(
+            &self.wallet,
+            &self.input_mint,
+            &self.input_token_program()?,
+        ))
+    }
+
+    pub fn output_token_account(&self) -> Result<Pubkey> {
+        Ok(get_associated_token_address_with_program_id(
+            &self.wallet,
+            &self.output_mint,
+            &self.output_token_program()?,
+        ))
+    }
+
+    /// Amount passed to the swap and its slippage bound: the minimum output for
+    /// exact-in, the maximum input for exact-out.
+    pub fn amount_and_threshold(&self) -> (u64, u64) {
+        let slippage = self.options.slippage_bps as f64 / 10_000.0;
+        if self.base_in {
+            (
+                self.quote.in_amount,
+                amount_with_slippage(self.quote.out_amount, slippage, false),
+            )
+        } else {
+            (
+                self.quote.out_amount,
+                amount_with_slippage(self.quote.in_amount, slippage, true),
+            )
+        }
+    }
+
+    /// Number of tick arrays the swap walks, the current one included.
+    pub fn tick_arrays_crossed(&self) -> Result<usize> {
+        let amm_config = self
+            .pool_manager
+            .amm_config
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("AmmConfig not initialized"))?;
+        let tickarray_bitmap_extension = self
+            .pool_manager
+            .tickarray_bitmap_extension
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("TickArrayBitmapExtension not initialized"))?;
+        let mut tick_arrays = if self.zero_for_one() {
+            self.pool_manager.up_tick_arrays.clone()
+        } else {
+            self.pool_manager.down_tick_arrays.clone()
+        };
+        let loaded = tick_arrays.len();
+        let (amount, _) = self.amount_and_threshold();
+        simulate_swap(
+            amount,
+            None,
+            self.zero_for_one(),
+            self.base_in,
+            amm_config,
+            &self.pool_manager.pool_state,
+            tickarray_bitmap_extension,
+            &mut tick_arrays,
+        )
+        .map_err(|err| anyhow::anyhow!(err))?;
+        Ok(loaded - tick_arrays.len())
+    }
+
+    pub fn estimate_compute_units(&self) -> Result<u32> {
+        let tick_arrays_crossed = self.tick_arrays_crossed()? as u32;
+        let mut units =
+            SWAP_BASE_COMPUTE_UNITS + COMPUTE_UNITS_PER_TICK_ARRAY * tick_arrays_crossed;
+        if self.options.create_output_account || self.creates_wsol_account() {
+            units += CREATE_ATA_COMPUTE_UNITS;
+        }
+        if self.wraps_input() || self.unwraps_output() || self.options.memo.is_some() {
+            units += WRAP_SOL_COMPUTE_UNITS;
+        }
+        Ok(units.min(MAX_COMPUTE_UNITS))
+    }
+
+    fn wraps_input(&self) -> bool {
+        self.options.wrap_and_unwrap_sol && self.input_mint == spl_token::native_mint::id()
+    }
+
+    fn unwraps_output(&self) -> bool {
+        self.options.wrap_and_unwrap_sol && self.output_mint == spl_token::native_mint::id()
+    }
+
+    // only a wSOL ata created by this transaction is closed after the swap, created
+    // without the idempotent instruction so the transaction fails rather than close an
+    // account the wallet already had
+    fn creates_wsol_account(&self) -> bool {
+        (self.wraps_input() || self.unwraps_output()) && !self.options.wsol_account_exists
+    }
+
+    /// All instructions in execution order.
+    pub fn build_instructions(&self) -> Result<Vec<Instruction>> {
+        self.pool_manager
+            .check_swappable(self.options.unix_timestamp)?;
+        let compute_unit_limit = match self.options.compute_unit_limit {
+            Some(limit) => limit,
+            None => self.estimate_compute_units()?,
+        };
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            compute_unit_limit,
+        )];
+        if let Some(price) = self.options.compute_unit_price_micro_lamports {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+        }
+
+        let (amount, other_amount_threshold) = self.amount_and_threshold();
+        let input_token_account = self.input_token_account()?;
+        let output_token_account = self.output_token_account()?;
+        if self.wraps_input() {
+            // the most the swap can take from the input account
+            let lamports = if self.base_in {
+                amount
+            } else {
+                other_amount_threshold
+            };
+            if self.creates_wsol_account() {
+                instructions.push(create_associated_token_account(
+                    &self.wallet,
+                    &self.wallet,
+                    &self.input_mint,
+                    &spl_token::id(),
+                ));
+            }
+            instructions.push(system_instruction::transfer(
+                &self.wallet,
+                &input_token_account,
+                lamports,
+            ));
+            instructions.push(spl_token::instruction::sync_native(
+                &spl_token::id(),
+                &input_token_account,
+            )?);
+        }
+        if self.unwraps_output() && self.creates_wsol_account() {
+            instructions.push(create_associated_token_account(
+                &self.wallet,
+                &self.wallet,
+                &self.output_mint,
+                &spl_token::id(),
+            ));
+        } else if self.options.create_output_account {
+            instructions.push(create_associated_token_account_idempotent(
+                &self.wallet,
+                &self.wallet,
+                &self.output_mint,
+                &self.output_token_program()?,
+            ));
+        }
+
+        instructions.push(InstructionBuilder::build_swap_v2_instruction_at(
+            self.pool_manager,
+            self.wallet,
+            self.input_mint,
+            self.output_mint,
+            input_token_account,
+            output_token_account,
+            amount,
+            other_amount_threshold,
+            None,
+            self.base_in,
+            self.options.unix_timestamp,
+        )?);
+
+        // closing returns what is left of the wrapped input, or the SOL received
+        if self.creates_wsol_account() {
+            let token_account = if self.wraps_input() {
+                input_token_account
+            } else {
+                output_token_account
+            };
+            instructions.push(spl_token::instruction::close_account(
+                &spl_token::id(),
+                &token_account,
+                &self.wallet,
+                &self.wallet,
+                &[],
+            )?);
+        }
+        if let Some(memo) = &self.options.memo {
+            instructions.push(spl_memo::build_memo(memo.as_bytes(), &[]));
+        }
+        Ok(instructions)
+    }
+
+    /// Unsigned v0 transaction paid by the wallet, with placeholder signatures to be
+    /// filled in by the signer.
+    pub fn build_transaction(&self, recent_blockhash: Hash) -> Result<VersionedTransaction> {
+        self.build_transaction_with_lookup_tables(recent_blockhash, &[])
+    }
+
+    /// Same as `build_transaction`, with the accounts found in `lookup_tables` loaded
+    /// through them instead of listed in the message.
+    pub fn build_transaction_with_lookup_tables(
+        &self,
+        recent_blockhash: Hash,
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> Result<VersionedTransaction> {
+        compile_transaction(
+            &self.wallet,
+            &self.build_instructions()?,
+            lookup_tables,
+            recent_blockhash,
+        )
+    }
+}
+
+/// Compile `instructions` into an unsigned v0 transaction. Invoked programs and
+/// signers always stay in the message, anything else found in `lookup_tables` is
+/// referenced through them.
+pub fn compile_transaction(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<VersionedTransaction> {
+    let message = v0::Message::try_compile(payer, instructions, lookup_tables, recent_blockhash)?;
+    Ok(unsigned_transaction(VersionedMessage::V0(message)))
+}
+
+fn unsigned_transaction(message: VersionedMessage) -> VersionedTransaction {
+    let num_signatures = message.header().num_required_signatures as usize;
+    VersionedTransaction {
+        signatures: vec![Signature::default(); num_signatures],
+        message,
+    }
+}
@@ -1,5 +1,6 @@
-use anchor_lang::AccountDeserialize;
+use anchor_lang::{AccountDeserialize, AccountSerialize, ZeroCopy};
 use anyhow::Result;
+use rust_decimal::Decimal;
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::account::Account;
 use solana_sdk::pubkey::Pubkey;
@@ -16,6 +17,7 @@ use spl_token_2022::{
         non_transferable::{NonTransferable, NonTransferableAccount},
         permanent_delegate::PermanentDelegate,
         transfer_fee::{MAX_FEE_BASIS_POINTS, TransferFeeAmount, TransferFeeConfig},
+        transfer_hook::TransferHook,
     },
     state::Mint,
 };
@@ -40,13 +42,48 @@ pub enum ExtensionStruct {
     PermanentDelegate(PermanentDelegate),
     TransferFeeConfig(TransferFeeConfig),
     TransferFeeAmount(TransferFeeAmount),
+    TransferHook(TransferHook),
+    Other(ExtensionType),
 }
 
 pub fn deserialize_anchor_account<T: AccountDeserialize>(account: &Account) -> Result<T> {
-    let mut data: &[u8] = &account.data;
+    deserialize_anchor_account_data(&account.data)
+}
+
+pub fn deserialize_anchor_account_data<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    let mut data: &[u8] = data;
     T::try_deserialize(&mut data).map_err(Into::into)
 }
 
+pub fn serialize_anchor_account<T: AccountSerialize>(account: &T) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    account.try_serialize(&mut data)?;
+    Ok(data)
+}
+
+/// Rebuild the on-chain bytes of a zero copy account, discriminator included.
+pub fn serialize_zero_copy_account<T: ZeroCopy>(account: &T) -> Vec<u8> {
+    let mut data = T::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(account));
+    data
+}
+
+/// Serde helpers writing a pubkey as a base58 string, for `#[serde(with = "...")]`.
+pub mod serde_pubkey {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use solana_sdk::pubkey::Pubkey;
+
+    pub fn serialize<S: Serializer>(key: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&key.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug)]
 pub struct TransferFeeInfo {
     pub mint: Pubkey,
@@ -155,92 +192,88 @@ pub fn get_transfer_fee<'data, S: BaseState>(
     fee
 }
 
+/// Extensions of a mint or token account. Extensions without a variant are reported as
+/// `ExtensionStruct::Other`, see `mint::MintCapabilities` for what they imply.
 pub fn get_account_extensions<'data, S: BaseState>(
     account_state: &StateWithExtensions<'data, S>,
-) -> Vec<ExtensionStruct> {
+) -> Result<Vec<ExtensionStruct>> {
     let mut extensions: Vec<ExtensionStruct> = Vec::new();
-    let extension_types = account_state.get_extension_types().unwrap();
-    println!("extension_types:{:?}", extension_types);
+    let extension_types = account_state.get_extension_types()?;
     for extension_type in extension_types {
         match extension_type {
             ExtensionType::ConfidentialTransferAccount => {
-                let extension = account_state
-                    .get_extension::<ConfidentialTransferAccount>()
-                    .unwrap();
+                let extension = account_state.get_extension::<ConfidentialTransferAccount>()?;
                 extensions.push(ExtensionStruct::ConfidentialTransferAccount(*extension));
             }
             ExtensionType::ConfidentialTransferMint => {
-                let extension = account_state
-                    .get_extension::<ConfidentialTransferMint>()
-                    .unwrap();
+                let extension = account_state.get_extension::<ConfidentialTransferMint>()?;
                 extensions.push(ExtensionStruct::ConfidentialTransferMint(*extension));
             }
             ExtensionType::CpiGuard => {
-                let extension = account_state.get_extension::<CpiGuard>().unwrap();
+                let extension = account_state.get_extension::<CpiGuard>()?;
                 extensions.push(ExtensionStruct::CpiGuard(*extension));
             }
             ExtensionType::DefaultAccountState => {
-                let extension = account_state
-                    .get_extension::<DefaultAccountState>()
-                    .unwrap();
+                let extension = account_state.get_extension::<DefaultAccountState>()?;
                 extensions.push(ExtensionStruct::DefaultAccountState(*extension));
             }
             ExtensionType::ImmutableOwner => {
-                let extension = account_state.get_extension::<ImmutableOwner>().unwrap();
+                let extension = account_state.get_extension::<ImmutableOwner>()?;
                 extensions.push(ExtensionStruct::ImmutableOwner(*extension));
             }
             ExtensionType::InterestBearingConfig => {
-                let extension = account_state
-                    .get_extension::<InterestBearingConfig>()
-                    .unwrap();
+                let extension = account_state.get_extension::<InterestBearingConfig>()?;
                 extensions.push(ExtensionStruct::InterestBearingConfig(*extension));
             }
             ExtensionType::MemoTransfer => {
-                let extension = account_state.get_extension::<MemoTransfer>().unwrap();
+                let extension = account_state.get_extension::<MemoTransfer>()?;
                 extensions.push(ExtensionStruct::MemoTransfer(*extension));
             }
             ExtensionType::MintCloseAuthority => {
-                let extension = account_state.get_extension::<MintCloseAuthority>().unwrap();
+                let extension = account_state.get_extension::<MintCloseAuthority>()?;
                 extensions.push(ExtensionStruct::MintCloseAuthority(*extension));
             }
             ExtensionType::NonTransferable => {
-                let extension = account_state.get_extension::<NonTransferable>().unwrap();
+                let extension = account_state.get_extension::<NonTransferable>()?;
                 extensions.push(ExtensionStruct::NonTransferable(*extension));
             }
             ExtensionType::NonTransferableAccount => {
-                let extension = account_state
-                    .get_extension::<NonTransferableAccount>()
-                    .unwrap();
+                let extension = account_state.get_extension::<NonTransferableAccount>()?;
                 extensions.push(ExtensionStruct::NonTransferableAccount(*extension));
             }
             ExtensionType::PermanentDelegate => {
-                let extension = account_state.get_extension::<PermanentDelegate>().unwrap();
+                let extension = account_state.get_extension::<PermanentDelegate>()?;
                 extensions.push(ExtensionStruct::PermanentDelegate(*extension));
             }
             ExtensionType::TransferFeeConfig => {
-                let extension = account_state.get_extension::<TransferFeeConfig>().unwrap();
+                let extension = account_state.get_extension::<TransferFeeConfig>()?;
                 extensions.push(ExtensionStruct::TransferFeeConfig(*extension));
             }
             ExtensionType::TransferFeeAmount => {
-                let extension = account_state.get_extension::<TransferFeeAmount>().unwrap();
+                let extension = account_state.get_extension::<TransferFeeAmount>()?;
                 extensions.push(ExtensionStruct::TransferFeeAmount(*extension));
             }
+            ExtensionType::TransferHook => {
+                let extension = account_state.get_extension::<TransferHook>()?;
+                extensions.push(ExtensionStruct::TransferHook(*extension));
+            }
             _ => {
-                println!("unkonwn extension:{:#?}", extension_type);
+                extensions.push(ExtensionStruct::Other(extension_type));
             }
         }
     }
-    extensions
+    Ok(extensions)
 }
 
 pub const Q_RATIO: f64 = 1.0001;
 
+#[deprecated(note = "use tick_to_decimal_price, exact and adjusted for decimals")]
 pub fn tick_to_price(tick: i32) -> f64 {
     Q_RATIO.powi(tick)
 }
 
 pub fn price_to_tick(price: f64) -> i32 {
-    price.log(Q_RATIO) as i32
+    price.log(Q_RATIO).floor() as i32
 }
 
 pub fn tick_to_sqrt_price(tick: i32) -> f64 {
@@ -256,7 +289,7 @@ pub fn tick_with_spacing(tick: i32, tick_spacing: i32) -> i32 {
 }
 
 pub fn multipler(decimals: u8) -> f64 {
-    (10_i32).checked_pow(decimals.try_into().unwrap()).unwrap() as f64
+    10_f64.powi(decimals as i32)
 }
 
 pub fn price_to_x64(price: f64) -> u128 {
@@ -267,17 +300,143 @@ pub fn from_x64_price(price: u128) -> f64 {
     price as f64 / fixed_point_64::Q64 as f64
 }
 
+#[deprecated(note = "use decimal_price_to_sqrt_price_x64, exact and rounded down")]
 pub fn price_to_sqrt_price_x64(price: f64, decimals_0: u8, decimals_1: u8) -> u128 {
     let price_with_decimals = price * multipler(decimals_1) / multipler(decimals_0);
     price_to_x64(price_with_decimals.sqrt())
 }
 
+#[deprecated(note = "use sqrt_price_x64_to_decimal_price, exact and rounded down")]
 pub fn sqrt_price_x64_to_price(price: u128, decimals_0: u8, decimals_1: u8) -> f64 {
     from_x64_price(price).powi(2) * multipler(decimals_0) / multipler(decimals_1)
 }
 
+// beyond this the decimal adjustment no longer fits the U512 intermediates
+const MAX_DECIMALS_DIFFERENCE: u32 = 30;
+// digits after the point a Decimal can carry
+const MAX_DECIMAL_SCALE: u32 = 28;
+
+fn pow10(exponent: u32) -> U512 {
+    U512::from(10u8).pow(U512::from(exponent))
+}
+
+// 10^(decimals_0 - decimals_1) as a numerator and denominator pair
+fn decimals_ratio(decimals_0: u8, decimals_1: u8) -> Result<(U512, U512)> {
+    let difference = (decimals_0 as i32 - decimals_1 as i32).unsigned_abs();
+    if difference > MAX_DECIMALS_DIFFERENCE {
+        return Err(anyhow::anyhow!(
+            "Mint decimals {} and {} too far apart",
+            decimals_0,
+            decimals_1
+        ));
+    }
+    if decimals_0 >= decimals_1 {
+        Ok((pow10(difference), U512::one()))
+    } else {
+        Ok((U512::one(), pow10(difference)))
+    }
+}
+
+/// Exact price of `sqrt_price_x64` in mint 1 per mint 0, adjusted for decimals and
+/// rounded down to the most fractional digits a `Decimal` holds at that magnitude.
+pub fn sqrt_price_x64_to_decimal_price(
+    sqrt_price_x64: u128,
+    decimals_0: u8,
+    decimals_1: u8,
+) -> Result<Decimal> {
+    let (numerator, denominator) = decimals_ratio(decimals_0, decimals_1)?;
+    let numerator = U512::from(sqrt_price_x64) * U512::from(sqrt_price_x64) * numerator;
+    let denominator = (U512::one() << 128) * denominator;
+    let max_mantissa = U512::from(Decimal::MAX.mantissa() as u128);
+    for scale in (0..=MAX_DECIMAL_SCALE).rev() {
+        let mantissa = numerator * pow10(scale) / denominator;
+        if mantissa <= max_mantissa {
+            return Ok(Decimal::from_i128_with_scale(
+                mantissa.as_u128() as i128,
+                scale,
+            ));
+        }
+    }
+    Err(anyhow::anyhow!(
+        "Price at sqrt price {} does not fit a Decimal",
+        sqrt_price_x64
+    ))
+}
+
+/// Exact sqrt price, as a Q64.64, of `price` in mint 1 per mint 0 adjusted for
+/// decimals. Rounded down, so the sqrt price never exceeds the price given.
+pub fn decimal_price_to_sqrt_price_x64(
+    price: Decimal,
+    decimals_0: u8,
+    decimals_1: u8,
+) -> Result<u128> {
+    if price <= Decimal::ZERO {
+        return Err(anyhow::anyhow!("Price {} is not positive", price));
+    }
+    // sqrt_price_x64 = floor(sqrt(price * 10^(decimals_1 - decimals_0) * 2^128))
+    let (numerator, denominator) = decimals_ratio(decimals_0, decimals_1)?;
+    let price_x128 = U512::from(price.mantissa() as u128) * (U512::one() << 128) * denominator
+        / (pow10(price.scale()) * numerator);
+    let sqrt_price_x64 = price_x128.integer_sqrt();
+    if sqrt_price_x64 > U512::from(u128::MAX) {
+        return Err(anyhow::anyhow!("Price {} out of range", price));
+    }
+    Ok(sqrt_price_x64.as_u128())
+}
+
+/// Exact price at `tick` in mint 1 per mint 0, adjusted for decimals.
+pub fn tick_to_decimal_price(tick: i32, decimals_0: u8, decimals_1: u8) -> Result<Decimal> {
+    let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(tick)
+        .map_err(|_| anyhow::anyhow!("Tick {} out of range", tick))?;
+    sqrt_price_x64_to_decimal_price(sqrt_price_x64, decimals_0, decimals_1)
+}
+
+/// Greatest tick whose price does not exceed `price`, in mint 1 per mint 0 adjusted
+/// for decimals.
+pub fn decimal_price_to_tick(price: Decimal, decimals_0: u8, decimals_1: u8) -> Result<i32> {
+    let sqrt_price_x64 = decimal_price_to_sqrt_price_x64(price, decimals_0, decimals_1)?;
+    if !(tick_math::MIN_SQRT_PRICE_X64..tick_math::MAX_SQRT_PRICE_X64).contains(&sqrt_price_x64) {
+        return Err(anyhow::anyhow!("Price {} out of range", price));
+    }
+    tick_math::get_tick_at_sqrt_price(sqrt_price_x64)
+        .map_err(|_| anyhow::anyhow!("Price {} out of range", price))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickRounding {
+    Down,
+    Up,
+    // ties round up
+    Nearest,
+}
+
+/// A multiple of `tick_spacing` next to `tick`, rounded as asked and kept within the
+/// usable tick range.
+pub fn usable_tick(tick: i32, tick_spacing: u16, rounding: TickRounding) -> i32 {
+    let tick_spacing = tick_spacing as i32;
+    let lower = tick_with_spacing(tick, tick_spacing);
+    let upper = if lower == tick {
+        lower
+    } else {
+        lower + tick_spacing
+    };
+    let rounded = match rounding {
+        TickRounding::Down => lower,
+        TickRounding::Up => upper,
+        TickRounding::Nearest if tick - lower < upper - tick => lower,
+        TickRounding::Nearest => upper,
+    };
+    // MIN_TICK is -MAX_TICK, so the range is symmetric
+    let max_usable = tick_with_spacing(tick_math::MAX_TICK, tick_spacing);
+    rounded.clamp(-max_usable, max_usable)
+}
+
+pub fn nearest_usable_tick(tick: i32, tick_spacing: u16) -> i32 {
+    usable_tick(tick, tick_spacing, TickRounding::Nearest)
+}
+
 // the top level state of the swap, the results of which are recorded in storage at the end
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct SwapState {
     // the amount remaining to be swapped in/out of the input/output asset
     pub amount_specified_remaining: u64,
@@ -289,8 +448,11 @@ pub struct SwapState {
     pub tick: i32,
     // the current liquidity in range
     pub liquidity: u128,
-
+    // trade fee paid, protocol and fund fee included
     pub fee_calculated: u64,
+    // parts of fee_calculated owed to the protocol and the fund, the rest goes to LPs
+    pub protocol_fee: u64,
+    pub fund_fee: u64,
 }
 #[derive(Default)]
 struct StepComputations {
@@ -320,38 +482,62 @@ pub fn get_out_put_amount_fee_and_remaining_accounts(
     tickarray_bitmap_extension: &TickArrayBitmapExtension,
     tick_arrays: &mut VecDeque<TickArrayState>,
 ) -> Result<(u64, u64), &'static str> {
+    let state = simulate_swap(
+        input_amount,
+        sqrt_price_limit_x64,
+        zero_for_one,
+        is_base_input,
+        pool_config,
+        pool_state,
+        tickarray_bitmap_extension,
+        tick_arrays,
+    )?;
+
+    Ok((state.amount_calculated, state.fee_calculated))
+}
+
+/// Run the swap over the loaded tick arrays and return the final swap state,
+/// including the price and tick the pool would end at.
+pub fn simulate_swap(
+    input_amount: u64,
+    sqrt_price_limit_x64: Option<u128>,
+    zero_for_one: bool,
+    is_base_input: bool,
+    pool_config: &AmmConfig,
+    pool_state: &PoolState,
+    tickarray_bitmap_extension: &TickArrayBitmapExtension,
+    tick_arrays: &mut VecDeque<TickArrayState>,
+) -> Result<SwapState, &'static str> {
     let (is_pool_current_tick_array, current_vaild_tick_array_start_index) = pool_state
         .get_first_initialized_tick_array(&Some(*tickarray_bitmap_extension), zero_for_one)
-        .unwrap();
+        .map_err(|_| "first initialized tick array not found")?;
 
-    let (amount_calculated, fee_amount) = swap_compute(
+    swap_compute(
         zero_for_one,
         is_base_input,
         is_pool_current_tick_array,
-        pool_config.trade_fee_rate,
+        pool_config,
         input_amount,
         current_vaild_tick_array_start_index,
         sqrt_price_limit_x64.unwrap_or(0),
         pool_state,
         tickarray_bitmap_extension,
         tick_arrays,
-    )?;
-
-    Ok((amount_calculated, fee_amount))
+    )
 }
 
 fn swap_compute(
     zero_for_one: bool,
     is_base_input: bool,
     is_pool_current_tick_array: bool,
-    fee1: u32,
+    amm_config: &AmmConfig,
     amount_specified: u64,
-    current_vaild_tick_array_start_index: i32,
+    mut current_vaild_tick_array_start_index: i32,
     sqrt_price_limit_x64: u128,
     pool_state: &PoolState,
     tickarray_bitmap_extension: &TickArrayBitmapExtension,
     tick_arrays: &mut VecDeque<TickArrayState>,
-) -> Result<(u64, u64), &'static str> {
+) -> Result<SwapState, &'static str> {
     if amount_specified == 0 {
         return Result::Err("amountSpecified must not be 0");
     }
@@ -388,9 +574,11 @@ fn swap_compute(
         tick: pool_state.tick_current,
         liquidity: pool_state.liquidity,
         fee_calculated: 0,
+        protocol_fee: 0,
+        fund_fee: 0,
     };
 
-    let mut tick_array_current = tick_arrays.pop_front().unwrap();
+    let mut tick_array_current = tick_arrays.pop_front().ok_or("tick array not loaded")?;
     if tick_array_current.start_tick_index != current_vaild_tick_array_start_index {
         return Result::Err("tick array start tick index does not match");
     }
@@ -427,19 +615,20 @@ fn swap_compute(
             }
         };
         if !next_initialized_tick.is_initialized() {
-            let current_vaild_tick_array_start_index = pool_state
+            let next_vaild_tick_array_start_index = pool_state
                 .next_initialized_tick_array_start_index(
                     &Some(*tickarray_bitmap_extension),
                     current_vaild_tick_array_start_index,
                     zero_for_one,
                 )
                 .unwrap();
-            tick_array_current = tick_arrays.pop_front().unwrap();
-            if current_vaild_tick_array_start_index.is_none() {
+            if next_vaild_tick_array_start_index.is_none() {
                 return Result::Err("tick array start tick index out of range limit");
             }
-            if tick_array_current.start_tick_index != current_vaild_tick_array_start_index.unwrap()
-            {
+            // advance the cursor, otherwise a third array is compared against the second's index
+            current_vaild_tick_array_start_index = next_vaild_tick_array_start_index.unwrap();
+            tick_array_current = tick_arrays.pop_front().ok_or("tick array not loaded")?;
+            if tick_array_current.start_tick_index != current_vaild_tick_array_start_index {
                 return Result::Err("tick array start tick index does not match");
             }
             // tick_array_start_index_vec.push_back(tick_array_current.start_tick_index);
@@ -471,7 +660,7 @@ fn swap_compute(
             target_price,
             state.liquidity,
             state.amount_specified_remaining,
-            fee1,
+            amm_config.trade_fee_rate,
             is_base_input,
             zero_for_one,
             1,
@@ -502,6 +691,15 @@ fn swap_compute(
                 .unwrap();
         }
         state.fee_calculated = state.fee_calculated.checked_add(step.fee_amount).unwrap();
+        // as the program does, the protocol share is taken from the step's fee and the fund
+        // share from what is left of it, both rounded down
+        let share_of_fee = |fee_amount: u64, fee_rate: u32| {
+            (fee_amount as u128 * fee_rate as u128 / FEE_RATE_DENOMINATOR_VALUE as u128) as u64
+        };
+        let protocol_fee = share_of_fee(step.fee_amount, amm_config.protocol_fee_rate);
+        let fund_fee = share_of_fee(step.fee_amount - protocol_fee, amm_config.fund_fee_rate);
+        state.protocol_fee = state.protocol_fee.checked_add(protocol_fee).unwrap();
+        state.fund_fee = state.fund_fee.checked_add(fund_fee).unwrap();
 
         if state.sqrt_price_x64 == step.sqrt_price_next_x64 {
             // if the tick is initialized, run the tick transition
@@ -526,5 +724,5 @@ fn swap_compute(
         loop_count += 1;
     }
 
-    Ok((state.amount_calculated, state.fee_calculated))
+    Ok(state)
 }
@@ -0,0 +1,403 @@
+//! In-memory pools shared by the integration tests.
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+
+use solana_sdk::{account::Account, program_option::COption, program_pack::Pack, pubkey::Pubkey};
+use spl_token_2022::{
+    extension::{ExtensionType, StateWithExtensionsMut},
+    state::Mint,
+};
+use swap_io_clmm::{
+    libraries::tick_math,
+    states::{
+        AMM_CONFIG_SEED, AmmConfig, OBSERVATION_SEED, ObservationState, POOL_SEED,
+        POOL_TICK_ARRAY_BITMAP_SEED, PoolState, TICK_ARRAY_SEED, TickArrayBitmapExtension,
+        TickArrayState, TickState,
+    },
+};
+use swap_io_clmm_rust_sdk::{
+    pool::PoolManager,
+    utils::{serialize_anchor_account, serialize_zero_copy_account, tick_with_spacing},
+};
+
+pub const TRADE_FEE_RATE: u32 = 2500;
+// shares of the trade fee, in hundredths of a bip
+pub const PROTOCOL_FEE_RATE: u32 = 120_000;
+pub const FUND_FEE_RATE: u32 = 40_000;
+const TICK_ARRAY_BITMAP_OFFSET: i32 = 512;
+
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedPool {
+    pub tick_spacing: u16,
+    pub tick_current: i32,
+    // always spans the current tick so both directions have liquidity
+    pub wide_liquidity: u128,
+    pub positions: Vec<Position>,
+}
+
+/// Addresses of a generated pool, derived the way the program derives them.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolKeys {
+    pub program_id: Pubkey,
+    pub pool_key: Pubkey,
+    pub pool_bump: u8,
+    pub amm_config: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub observation_key: Pubkey,
+}
+
+impl PoolKeys {
+    pub fn new(
+        token_mint_0: Pubkey,
+        token_mint_1: Pubkey,
+        token_vault_0: Pubkey,
+        token_vault_1: Pubkey,
+    ) -> Self {
+        Self::with_program_id(
+            swap_io_clmm::id(),
+            token_mint_0,
+            token_mint_1,
+            token_vault_0,
+            token_vault_1,
+        )
+    }
+
+    /// Keys of a pool under another deployment of the program.
+    pub fn with_program_id(
+        program_id: Pubkey,
+        token_mint_0: Pubkey,
+        token_mint_1: Pubkey,
+        token_vault_0: Pubkey,
+        token_vault_1: Pubkey,
+    ) -> Self {
+        let amm_config = Pubkey::find_program_address(
+            &[AMM_CONFIG_SEED.as_bytes(), &0u16.to_be_bytes()],
+            &program_id,
+        )
+        .0;
+        let (pool_key, pool_bump) = Pubkey::find_program_address(
+            &[
+                POOL_SEED.as_bytes(),
+                amm_config.as_ref(),
+                token_mint_0.as_ref(),
+                token_mint_1.as_ref(),
+            ],
+            &program_id,
+        );
+        let observation_key = Pubkey::find_program_address(
+            &[OBSERVATION_SEED.as_bytes(), pool_key.as_ref()],
+            &program_id,
+        )
+        .0;
+        PoolKeys {
+            program_id,
+            pool_key,
+            pool_bump,
+            amm_config,
+            token_mint_0,
+            token_mint_1,
+            token_vault_0,
+            token_vault_1,
+            observation_key,
+        }
+    }
+
+    pub fn unique() -> Self {
+        Self::new(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        )
+    }
+
+    pub fn unique_with_program_id(program_id: Pubkey) -> Self {
+        Self::with_program_id(
+            program_id,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        )
+    }
+
+    pub fn tick_array(&self, start_index: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                TICK_ARRAY_SEED.as_bytes(),
+                self.pool_key.as_ref(),
+                &start_index.to_be_bytes(),
+            ],
+            &self.program_id,
+        )
+        .0
+    }
+
+    pub fn tickarray_bitmap_extension(&self) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+                self.pool_key.as_ref(),
+            ],
+            &self.program_id,
+        )
+        .0
+    }
+}
+
+/// Program owned accounts of a generated pool.
+pub struct PoolAccounts {
+    pub pool_state: Account,
+    pub amm_config: Account,
+    pub tickarray_bitmap_extension: Account,
+    pub observation: Account,
+    pub tick_arrays: Vec<(Pubkey, Account)>,
+}
+
+impl PoolAccounts {
+    pub fn all(&self, keys: &PoolKeys) -> Vec<(Pubkey, Account)> {
+        let mut accounts = vec![
+            (keys.pool_key, self.pool_state.clone()),
+            (keys.amm_config, self.amm_config.clone()),
+            (
+                keys.tickarray_bitmap_extension(),
+                self.tickarray_bitmap_extension.clone(),
+            ),
+            (keys.observation_key, self.observation.clone()),
+        ];
+        accounts.extend(self.tick_arrays.iter().cloned());
+        accounts
+    }
+}
+
+impl GeneratedPool {
+    pub fn all_positions(&self) -> Vec<Position> {
+        let span = TickArrayState::tick_count(self.tick_spacing);
+        let spacing = self.tick_spacing as i32;
+        let mut positions = vec![Position {
+            tick_lower: tick_with_spacing(self.tick_current - 2 * span, spacing),
+            tick_upper: tick_with_spacing(self.tick_current + 2 * span, spacing) + spacing,
+            liquidity: self.wide_liquidity,
+        }];
+        positions.extend(self.positions.iter().cloned());
+        positions
+    }
+
+    // largest amount that stays well inside the wide position
+    pub fn max_amount(&self) -> u64 {
+        (self.wide_liquidity / 1000).min(u64::MAX as u128) as u64
+    }
+
+    pub fn accounts(&self, keys: &PoolKeys) -> PoolAccounts {
+        let spacing = self.tick_spacing as i32;
+
+        let mut ticks: BTreeMap<i32, (i128, u128)> = BTreeMap::new();
+        let mut liquidity = 0u128;
+        for position in self.all_positions() {
+            let lower = ticks.entry(position.tick_lower).or_default();
+            lower.0 += position.liquidity as i128;
+            lower.1 += position.liquidity;
+            let upper = ticks.entry(position.tick_upper).or_default();
+            upper.0 -= position.liquidity as i128;
+            upper.1 += position.liquidity;
+            if position.tick_lower <= self.tick_current && self.tick_current < position.tick_upper {
+                liquidity += position.liquidity;
+            }
+        }
+
+        let mut tick_arrays: BTreeMap<i32, TickArrayState> = BTreeMap::new();
+        for (tick, (liquidity_net, liquidity_gross)) in ticks {
+            let start_index = TickArrayState::get_array_start_index(tick, self.tick_spacing);
+            let tick_array = tick_arrays.entry(start_index).or_insert_with(|| {
+                let mut tick_array: TickArrayState = bytemuck::Zeroable::zeroed();
+                tick_array.pool_id = keys.pool_key;
+                tick_array.start_tick_index = start_index;
+                tick_array
+            });
+            let mut tick_state: TickState = bytemuck::Zeroable::zeroed();
+            tick_state.tick = tick;
+            tick_state.liquidity_net = liquidity_net;
+            tick_state.liquidity_gross = liquidity_gross;
+            let mut tick_states = tick_array.ticks;
+            tick_states[((tick - start_index) / spacing) as usize] = tick_state;
+            tick_array.ticks = tick_states;
+            tick_array.initialized_tick_count += 1;
+        }
+
+        let mut tick_array_bitmap = [0u64; 16];
+        for start_index in tick_arrays.keys() {
+            let offset = (start_index / TickArrayState::tick_count(self.tick_spacing)
+                + TICK_ARRAY_BITMAP_OFFSET) as usize;
+            tick_array_bitmap[offset / 64] |= 1 << (offset % 64);
+        }
+
+        let mut pool_state: PoolState = bytemuck::Zeroable::zeroed();
+        pool_state.bump = [keys.pool_bump];
+        pool_state.amm_config = keys.amm_config;
+        pool_state.token_mint_0 = keys.token_mint_0;
+        pool_state.token_mint_1 = keys.token_mint_1;
+        pool_state.token_vault_0 = keys.token_vault_0;
+        pool_state.token_vault_1 = keys.token_vault_1;
+        pool_state.observation_key = keys.observation_key;
+        pool_state.mint_decimals_0 = 6;
+        pool_state.mint_decimals_1 = 6;
+        pool_state.tick_spacing = self.tick_spacing;
+        pool_state.liquidity = liquidity;
+        pool_state.sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(self.tick_current).unwrap();
+        pool_state.tick_current = self.tick_current;
+        pool_state.tick_array_bitmap = tick_array_bitmap;
+
+        let amm_config = AmmConfig {
+            trade_fee_rate: TRADE_FEE_RATE,
+            protocol_fee_rate: PROTOCOL_FEE_RATE,
+            fund_fee_rate: FUND_FEE_RATE,
+            tick_spacing: self.tick_spacing,
+            ..Default::default()
+        };
+        let mut tickarray_bitmap_extension: TickArrayBitmapExtension = bytemuck::Zeroable::zeroed();
+        tickarray_bitmap_extension.pool_id = keys.pool_key;
+        let mut observation: ObservationState = bytemuck::Zeroable::zeroed();
+        observation.pool_id = keys.pool_key;
+
+        PoolAccounts {
+            pool_state: program_account(serialize_zero_copy_account(&pool_state), keys.program_id),
+            amm_config: program_account(
+                serialize_anchor_account(&amm_config).unwrap(),
+                keys.program_id,
+            ),
+            tickarray_bitmap_extension: program_account(
+                serialize_zero_copy_account(&tickarray_bitmap_extension),
+                keys.program_id,
+            ),
+            observation: program_account(
+                serialize_zero_copy_account(&observation),
+                keys.program_id,
+            ),
+            tick_arrays: tick_arrays
+                .values()
+                .map(|tick_array| {
+                    (
+                        keys.tick_array(tick_array.start_tick_index),
+                        program_account(serialize_zero_copy_account(tick_array), keys.program_id),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Load the pool through `PoolManager::new`/`update`, feeding it exactly the
+    /// tick arrays it navigated to.
+    pub fn load(
+        &self,
+        keys: &PoolKeys,
+        accounts: &PoolAccounts,
+        mint0: &Account,
+        mint1: &Account,
+    ) -> PoolManager {
+        let mut pool_manager =
+            PoolManager::new(0, keys.pool_key, keys.program_id, &accounts.pool_state).unwrap();
+        let tick_array_accounts = |tick_array_keys: &[Pubkey]| -> Vec<Account> {
+            tick_array_keys
+                .iter()
+                .map(|key| {
+                    accounts
+                        .tick_arrays
+                        .iter()
+                        .find(|(tick_array_key, _)| tick_array_key == key)
+                        .map(|(_, account)| account.clone())
+                        .expect("pool manager navigated to a tick array that was never created")
+                })
+                .collect()
+        };
+        let up_ticks = tick_array_accounts(&pool_manager.up_tick_array_keys);
+        let down_ticks = tick_array_accounts(&pool_manager.down_tick_array_keys);
+        pool_manager
+            .update(
+                vec![
+                    &accounts.amm_config,
+                    mint0,
+                    mint1,
+                    &accounts.tickarray_bitmap_extension,
+                ],
+                up_ticks,
+                down_ticks,
+            )
+            .unwrap();
+        pool_manager
+    }
+
+    pub fn build(&self) -> PoolManager {
+        let keys = PoolKeys::unique();
+        let accounts = self.accounts(&keys);
+        self.load(&keys, &accounts, &mint_account(), &mint_account())
+    }
+}
+
+pub fn program_account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+pub fn mint_account() -> Account {
+    let mut data = vec![0u8; Mint::LEN];
+    Mint {
+        mint_authority: COption::None,
+        supply: u64::MAX,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    program_account(data, spl_token::id())
+}
+
+/// Token account holding `amount` of `mint`, owned by `owner`.
+pub fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    program_account(data, spl_token::id())
+}
+
+/// Token-2022 mint with `extension_types`, their values set by `init`.
+pub fn token_2022_mint_account(
+    extension_types: &[ExtensionType],
+    init: impl FnOnce(&mut StateWithExtensionsMut<Mint>),
+) -> Account {
+    let len = ExtensionType::try_calculate_account_len::<Mint>(extension_types).unwrap();
+    let mut data = vec![0u8; len];
+    let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+    init(&mut state);
+    state.base = Mint {
+        mint_authority: COption::None,
+        supply: u64::MAX,
+        decimals: 9,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    state.pack_base();
+    state.init_account_type().unwrap();
+    program_account(data, spl_token_2022::id())
+}
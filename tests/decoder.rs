@@ -0,0 +1,138 @@
+//! `InstructionBuilder` output read back through the decoder.
+
+mod common;
+
+use solana_sdk::{hash::Hash, message::Message, pubkey::Pubkey};
+use swap_io_clmm_rust_sdk::{
+    decoder::{AccountRole, ClmmInstruction, DecodedInstruction, SwapArgs},
+    instruction::InstructionBuilder,
+};
+
+use common::{GeneratedPool, PoolKeys, mint_account};
+
+#[test]
+fn decodes_built_swap_v2() {
+    let pool_manager = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 15,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    }
+    .build();
+    let pool_state = &pool_manager.pool_state;
+    let payer = Pubkey::new_unique();
+    let (source_token_account, destination_token_account) =
+        (Pubkey::new_unique(), Pubkey::new_unique());
+    let instruction = InstructionBuilder::build_swap_v2_instruction(
+        &pool_manager,
+        payer,
+        pool_state.token_mint_1,
+        pool_state.token_mint_0,
+        source_token_account,
+        destination_token_account,
+        1_000,
+        990,
+        None,
+        true,
+    )
+    .unwrap();
+
+    let decoded = DecodedInstruction::decode(&pool_manager.program_id, &instruction).unwrap();
+    assert_eq!(
+        decoded.instruction,
+        ClmmInstruction::SwapV2(SwapArgs {
+            amount: 1_000,
+            other_amount_threshold: 990,
+            sqrt_price_limit_x64: 0,
+            is_base_input: true,
+        })
+    );
+    assert_eq!(decoded.account(AccountRole::Payer), Some(payer));
+    assert_eq!(
+        decoded.account(AccountRole::PoolState),
+        Some(pool_manager.pool_key)
+    );
+    assert_eq!(
+        decoded.account(AccountRole::InputVault),
+        Some(pool_state.token_vault_1)
+    );
+    assert_eq!(
+        decoded.account(AccountRole::InputTokenAccount),
+        Some(source_token_account)
+    );
+    assert_eq!(
+        decoded.account(AccountRole::TickArrayBitmapExtension),
+        Some(pool_manager.tick_array_bitmap_extension())
+    );
+    assert_eq!(
+        decoded.tick_arrays(),
+        pool_manager.get_down_tick_array_keys()
+    );
+
+    // the same data sent to another program is not decoded
+    let mut foreign = instruction.clone();
+    foreign.program_id = Pubkey::new_unique();
+    let err = DecodedInstruction::decode(&pool_manager.program_id, &foreign).unwrap_err();
+    assert!(err.to_string().contains("not swap-io-clmm"));
+    assert_eq!(
+        AccountRole::TickArrayBitmapExtension.to_string(),
+        "tick_array_bitmap_extension"
+    );
+
+    // the same instruction compiled into a message
+    let message = Message::new_with_blockhash(&[instruction], Some(&payer), &Hash::default());
+    let compiled = DecodedInstruction::decode_compiled(
+        &pool_manager.program_id,
+        &message.instructions[0],
+        &message.account_keys,
+    )
+    .unwrap();
+    assert_eq!(compiled, decoded);
+}
+
+#[test]
+fn decodes_swaps_of_another_deployment() {
+    let pool = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 15,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    };
+    let program_id = Pubkey::new_unique();
+    let keys = PoolKeys::unique_with_program_id(program_id);
+    let pool_manager = pool.load(
+        &keys,
+        &pool.accounts(&keys),
+        &mint_account(),
+        &mint_account(),
+    );
+    let instruction = InstructionBuilder::build_swap_v2_instruction(
+        &pool_manager,
+        Pubkey::new_unique(),
+        keys.token_mint_0,
+        keys.token_mint_1,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        1_000,
+        990,
+        None,
+        true,
+    )
+    .unwrap();
+    assert_eq!(instruction.program_id, program_id);
+
+    let decoded = DecodedInstruction::decode(&program_id, &instruction).unwrap();
+    // the bitmap extension and tick arrays are derived under that program id
+    assert_eq!(
+        decoded.account(AccountRole::TickArrayBitmapExtension),
+        Some(keys.tickarray_bitmap_extension())
+    );
+    assert_eq!(decoded.tick_arrays(), pool_manager.get_up_tick_array_keys());
+    assert!(DecodedInstruction::decode(&swap_io_clmm::id(), &instruction).is_err());
+}
+
+#[test]
+fn rejects_unknown_discriminator() {
+    assert!(ClmmInstruction::decode(&[0u8; 16]).is_err());
+    assert!(ClmmInstruction::decode(&[1u8; 4]).is_err());
+}
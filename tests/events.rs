@@ -0,0 +1,69 @@
+//! Decoding of `emit!` events from transaction logs.
+
+use anchor_lang::{AnchorSerialize, Discriminator};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm::states::SwapEvent;
+use swap_io_clmm_rust_sdk::events::{ClmmEvent, swap_event_amounts};
+
+fn swap_event(pool_state: Pubkey) -> SwapEvent {
+    SwapEvent {
+        pool_state,
+        sender: Pubkey::new_unique(),
+        token_account_0: Pubkey::new_unique(),
+        token_account_1: Pubkey::new_unique(),
+        amount_0: 1_000,
+        transfer_fee_0: 0,
+        amount_1: 990,
+        transfer_fee_1: 10,
+        zero_for_one: true,
+        sqrt_price_x64: 1 << 64,
+        liquidity: 1_000_000,
+        tick: -3,
+    }
+}
+
+fn program_data(event: &SwapEvent) -> String {
+    let mut data = SwapEvent::DISCRIMINATOR.to_vec();
+    event.serialize(&mut data).unwrap();
+    format!("Program data: {}", STANDARD.encode(data))
+}
+
+#[test]
+fn parses_events_of_the_program_only() {
+    let program_id = swap_io_clmm::id();
+    let other_program = Pubkey::new_unique();
+    let pool_state = Pubkey::new_unique();
+    let event = swap_event(pool_state);
+    let logs = vec![
+        format!("Program {} invoke [1]", other_program),
+        // emitted by the router, not by the clmm program
+        program_data(&event),
+        format!("Program {} invoke [2]", program_id),
+        "Program log: Instruction: SwapV2".to_string(),
+        format!("Program {} invoke [3]", spl_token_2022::id()),
+        format!("Program {} success", spl_token_2022::id()),
+        program_data(&event),
+        format!(
+            "Program {} consumed 60000 of 200000 compute units",
+            program_id
+        ),
+        format!("Program {} success", program_id),
+        format!("Program {} success", other_program),
+    ];
+
+    let events = ClmmEvent::parse_logs(&program_id, &logs).unwrap();
+    assert_eq!(events.len(), 1);
+    let ClmmEvent::Swap(decoded) = &events[0] else {
+        panic!("expected a swap event, got {:?}", events[0]);
+    };
+    assert_eq!(decoded.pool_state, pool_state);
+    assert_eq!(decoded.tick, -3);
+    assert_eq!(swap_event_amounts(decoded), (1_000, 990));
+}
+
+#[test]
+fn skips_unknown_discriminators() {
+    assert!(ClmmEvent::decode(&[7u8; 16]).unwrap().is_none());
+    assert!(ClmmEvent::decode(&[7u8; 4]).is_err());
+}
@@ -0,0 +1,192 @@
+//! Fee tiers decoded from AmmConfig accounts.
+
+mod common;
+
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    pubkey::Pubkey,
+};
+use swap_io_clmm::states::AmmConfig;
+use swap_io_clmm_rust_sdk::{
+    fee_tier::{
+        FeeTier, FeeTierChange, amm_config_address, amm_config_filters, fee_tier_changes,
+        fee_tiers_from_accounts,
+    },
+    pool::PoolManager,
+    registry::PoolRegistry,
+    utils::serialize_anchor_account,
+};
+
+use common::{
+    FUND_FEE_RATE, GeneratedPool, PROTOCOL_FEE_RATE, PoolKeys, TRADE_FEE_RATE, program_account,
+};
+
+fn pool() -> GeneratedPool {
+    GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 0,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    }
+}
+
+fn amm_config(index: u16, tick_spacing: u16, trade_fee_rate: u32) -> AmmConfig {
+    AmmConfig {
+        index,
+        tick_spacing,
+        trade_fee_rate,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn pool_exposes_its_fee_tier() {
+    let pool = pool();
+    let keys = PoolKeys::unique();
+    let mint = common::mint_account();
+    let pool_manager = pool.load(&keys, &pool.accounts(&keys), &mint, &mint);
+    assert_eq!(amm_config_address(&keys.program_id, 0), keys.amm_config);
+    let fee_tier = pool_manager.fee_tier().unwrap();
+    assert_eq!(
+        fee_tier,
+        FeeTier {
+            amm_config: keys.amm_config,
+            index: 0,
+            tick_spacing: 10,
+            trade_fee_rate: TRADE_FEE_RATE,
+            protocol_fee_rate: PROTOCOL_FEE_RATE,
+            fund_fee_rate: FUND_FEE_RATE,
+        }
+    );
+    assert_eq!(fee_tier.trade_fee_pct().to_string(), "0.002500");
+
+    // another tier, or the right one with a different spacing, is not the pool's config
+    assert!(
+        pool_manager
+            .check_amm_config(&amm_config(1, 10, TRADE_FEE_RATE))
+            .is_err()
+    );
+    assert!(
+        pool_manager
+            .check_amm_config(&amm_config(0, 60, TRADE_FEE_RATE))
+            .is_err()
+    );
+    assert!(
+        pool_manager
+            .check_amm_config(&amm_config(0, 10, TRADE_FEE_RATE))
+            .is_ok()
+    );
+}
+
+#[test]
+fn update_rejects_a_foreign_amm_config() {
+    let pool = pool();
+    let keys = PoolKeys::unique();
+    let mut accounts = pool.accounts(&keys);
+    accounts.amm_config = program_account(
+        serialize_anchor_account(&amm_config(3, 10, TRADE_FEE_RATE)).unwrap(),
+        keys.program_id,
+    );
+    let mint = common::mint_account();
+    let mut pool_manager =
+        PoolManager::new(0, keys.pool_key, keys.program_id, &accounts.pool_state).unwrap();
+    assert!(
+        pool_manager
+            .update(
+                vec![
+                    &accounts.amm_config,
+                    &mint,
+                    &mint,
+                    &accounts.tickarray_bitmap_extension
+                ],
+                vec![],
+                vec![],
+            )
+            .is_err()
+    );
+}
+
+#[test]
+fn fee_tiers_decode_and_diff() {
+    let program_id = swap_io_clmm::id();
+    let account =
+        |config: &AmmConfig| program_account(serialize_anchor_account(config).unwrap(), program_id);
+    let configs = [amm_config(0, 1, 100), amm_config(1, 60, 2_500)];
+    let accounts = vec![
+        (
+            amm_config_address(&program_id, 0),
+            Some(account(&configs[0])),
+        ),
+        (
+            amm_config_address(&program_id, 1),
+            Some(account(&configs[1])),
+        ),
+        (amm_config_address(&program_id, 2), None),
+    ];
+    let fee_tiers = fee_tiers_from_accounts(&program_id, accounts).unwrap();
+    assert_eq!(fee_tiers.len(), 2);
+    assert_eq!(fee_tiers[1].tick_spacing, 60);
+    assert_eq!(fee_tiers[1].trade_fee_rate, 2_500);
+
+    // an account at the wrong address
+    let misplaced = vec![(Pubkey::new_unique(), Some(account(&configs[0])))];
+    assert!(fee_tiers_from_accounts(&program_id, misplaced).is_err());
+
+    let mut updated = fee_tiers.clone();
+    updated[1].trade_fee_rate = 3_000;
+    assert_eq!(fee_tier_changes(&fee_tiers, &fee_tiers), vec![]);
+    assert_eq!(
+        fee_tier_changes(&fee_tiers, &updated),
+        vec![FeeTierChange {
+            old: fee_tiers[1],
+            new: updated[1],
+        }]
+    );
+}
+
+#[test]
+fn fee_tiers_are_found_across_index_gaps() {
+    let program_id = Pubkey::new_unique();
+    let account =
+        |config: &AmmConfig| program_account(serialize_anchor_account(config).unwrap(), program_id);
+    let pool = pool();
+    let pool_state = pool
+        .accounts(&PoolKeys::unique_with_program_id(program_id))
+        .pool_state;
+
+    // getProgramAccounts returns the configs, whatever their indexes, and nothing else
+    let allowed = |account: &Account| {
+        amm_config_filters()
+            .iter()
+            .all(|filter| filter.allows(&AccountSharedData::from(account.clone())))
+    };
+    assert!(allowed(&account(&amm_config(0, 1, 100))));
+    assert!(!allowed(&pool_state));
+
+    // in no particular order, with gaps around what used to be a batch boundary
+    let accounts = [250, 0, 100, 99].map(|index| {
+        (
+            amm_config_address(&program_id, index),
+            Some(account(&amm_config(index, 10, 2_500))),
+        )
+    });
+    let fee_tiers = fee_tiers_from_accounts(&program_id, accounts).unwrap();
+    let indexes: Vec<u16> = fee_tiers.iter().map(|fee_tier| fee_tier.index).collect();
+    assert_eq!(indexes, vec![0, 99, 100, 250]);
+}
+
+#[test]
+fn registry_groups_pools_by_amm_config() {
+    let mut registry = PoolRegistry::new();
+    let first = pool().build();
+    let second = pool().build();
+    let amm_config = first.pool_state.amm_config;
+    let mut expected = vec![first.pool_key, second.pool_key];
+    registry.insert(first);
+    registry.insert(second);
+
+    let mut pools = registry.pools_by_amm_config().remove(&amm_config).unwrap();
+    pools.sort();
+    expected.sort();
+    assert_eq!(pools, expected);
+}
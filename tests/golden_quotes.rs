@@ -0,0 +1,130 @@
+//! Replays recorded on-chain swaps against `QuoteCalculator`.
+//!
+//! Every `tests/fixtures/golden/*.json` file holds a `PoolSnapshot` captured at
+//! some slot and the swaps that executed against that exact state, e.g.
+//!
+//! ```json
+//! {
+//!   "description": "SOL/USDC 0.05% at slot 312000000",
+//!   "snapshot": { "epoch": 722, "pool_key": "...", ... },
+//!   "cases": [
+//!     {
+//!       "signature": "...",
+//!       "input_mint": "So11111111111111111111111111111111111111112",
+//!       "output_mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+//!       "base_in": true,
+//!       "amount": 1000000000,
+//!       "in_amount": 1000000000,
+//!       "out_amount": 171234567,
+//!       "fee_amount": 500000
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Amounts are the balance changes observed on-chain, so any change to
+//! `swap_compute` that diverges from the program shows up as a mismatch.
+//! `fee_amount` is optional, balance changes alone do not reveal it.
+//! `record_golden_fixture` in `program_test.rs` records `program_test.json`.
+
+use std::{fs, path::PathBuf};
+
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm_rust_sdk::{quote::QuoteCalculator, snapshot::PoolSnapshot};
+
+#[derive(Deserialize)]
+struct GoldenFixture {
+    description: String,
+    snapshot: PoolSnapshot,
+    cases: Vec<GoldenCase>,
+}
+
+#[derive(Deserialize)]
+struct GoldenCase {
+    #[serde(default)]
+    signature: Option<String>,
+    input_mint: String,
+    output_mint: String,
+    base_in: bool,
+    amount: u64,
+    in_amount: u64,
+    out_amount: u64,
+    #[serde(default)]
+    fee_amount: Option<u64>,
+}
+
+fn fixture_paths() -> Vec<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden");
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "json")
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn quotes_match_recorded_swaps() {
+    let paths = fixture_paths();
+    assert!(
+        !paths.is_empty(),
+        "no golden fixtures found in tests/fixtures/golden"
+    );
+    let mut failures = vec![];
+    let mut case_count = 0;
+    for path in paths {
+        let fixture: GoldenFixture =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let pool_manager = fixture.snapshot.to_pool_manager().unwrap();
+        for (index, case) in fixture.cases.iter().enumerate() {
+            case_count += 1;
+            let label = format!(
+                "{} [{}] case {} {}",
+                path.display(),
+                fixture.description,
+                index,
+                case.signature.as_deref().unwrap_or("")
+            );
+            let input_mint: Pubkey = case.input_mint.parse().unwrap();
+            let output_mint: Pubkey = case.output_mint.parse().unwrap();
+            match QuoteCalculator::calculate_quote(
+                input_mint,
+                output_mint,
+                case.base_in,
+                case.amount,
+                &pool_manager,
+            ) {
+                Ok(quote) => {
+                    let actual = (
+                        quote.in_amount,
+                        quote.out_amount,
+                        case.fee_amount.map(|_| quote.fee_amount),
+                    );
+                    let expected = (case.in_amount, case.out_amount, case.fee_amount);
+                    if actual != expected {
+                        failures.push(format!(
+                            "{}: (in, out, fee) expected {:?}, got {:?}",
+                            label, expected, actual
+                        ));
+                    }
+                }
+                Err(err) => failures.push(format!("{}: quote failed: {}", label, err)),
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} golden cases diverged:\n{}",
+        failures.len(),
+        case_count,
+        failures.join("\n")
+    );
+}
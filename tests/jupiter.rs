@@ -0,0 +1,113 @@
+#![cfg(feature = "jupiter")]
+
+mod common;
+
+use jupiter_amm_interface::{
+    AccountMap, Amm, AmmContext, ClockRef, KeyedAccount, QuoteParams, Swap, SwapMode, SwapParams,
+};
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm_rust_sdk::{jupiter::SwapIoClmmAmm, pool::PoolManager, quote::QuoteCalculator};
+
+use common::{GeneratedPool, PoolKeys, Position, mint_account};
+
+// returns the adapter and a PoolManager loaded directly from the same accounts
+fn loaded_amm() -> (SwapIoClmmAmm, PoolManager, PoolKeys) {
+    let pool = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: -25,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![Position {
+            tick_lower: -600,
+            tick_upper: 300,
+            liquidity: 3_000_000_000_000,
+        }],
+    };
+    let keys = PoolKeys::unique();
+    let accounts = pool.accounts(&keys);
+    let amm_context = AmmContext {
+        clock_ref: ClockRef::default(),
+    };
+    let mut amm = SwapIoClmmAmm::from_keyed_account(
+        &KeyedAccount {
+            key: keys.pool_key,
+            account: accounts.pool_state.clone(),
+            params: None,
+        },
+        &amm_context,
+    )
+    .unwrap();
+
+    let mut account_map = AccountMap::default();
+    account_map.extend(accounts.all(&keys));
+    account_map.insert(keys.token_mint_0, mint_account());
+    account_map.insert(keys.token_mint_1, mint_account());
+    for key in amm.get_accounts_to_update() {
+        assert!(account_map.contains_key(&key), "unknown account {}", key);
+    }
+    amm.update(&account_map).unwrap();
+    let pool_manager = pool.load(&keys, &accounts, &mint_account(), &mint_account());
+    (amm, pool_manager, keys)
+}
+
+#[test]
+fn quotes_match_quote_calculator() {
+    let (amm, expected, keys) = loaded_amm();
+    for (input_mint, output_mint) in [
+        (keys.token_mint_0, keys.token_mint_1),
+        (keys.token_mint_1, keys.token_mint_0),
+    ] {
+        for swap_mode in [SwapMode::ExactIn, SwapMode::ExactOut] {
+            let quote = amm
+                .quote(&QuoteParams {
+                    amount: 1_000_000,
+                    input_mint,
+                    output_mint,
+                    swap_mode,
+                })
+                .unwrap();
+            let expected_quote = QuoteCalculator::calculate_quote(
+                input_mint,
+                output_mint,
+                swap_mode == SwapMode::ExactIn,
+                1_000_000,
+                &expected,
+            )
+            .unwrap();
+            assert_eq!(quote.in_amount, expected_quote.in_amount);
+            assert_eq!(quote.out_amount, expected_quote.out_amount);
+            assert_eq!(quote.fee_amount, expected_quote.fee_amount);
+        }
+    }
+}
+
+#[test]
+fn swap_account_metas_start_with_program_and_payer() {
+    let (amm, _, keys) = loaded_amm();
+    let payer = Pubkey::new_unique();
+    let jupiter_program_id = Pubkey::new_unique();
+    let swap_and_account_metas = amm
+        .get_swap_and_account_metas(&SwapParams {
+            in_amount: 1_000_000,
+            out_amount: 990_000,
+            source_mint: keys.token_mint_0,
+            destination_mint: keys.token_mint_1,
+            source_token_account: Pubkey::new_unique(),
+            destination_token_account: Pubkey::new_unique(),
+            token_transfer_authority: payer,
+            open_order_address: None,
+            quote_mint_to_referrer: None,
+            jupiter_program_id: &jupiter_program_id,
+            missing_dynamic_accounts_as_default: false,
+        })
+        .unwrap();
+    assert!(matches!(swap_and_account_metas.swap, Swap::RaydiumClmmV2));
+    let account_metas = swap_and_account_metas.account_metas;
+    assert_eq!(account_metas[0].pubkey, keys.program_id);
+    assert_eq!(account_metas[1].pubkey, payer);
+    assert!(account_metas[1].is_signer);
+    assert_eq!(account_metas[3].pubkey, keys.pool_key);
+    assert_eq!(
+        account_metas.len() - 15,
+        amm.pool_manager.get_up_tick_array_keys().len()
+    );
+}
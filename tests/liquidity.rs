@@ -0,0 +1,85 @@
+//! Liquidity distribution and depth over the loaded tick arrays.
+
+mod common;
+
+use swap_io_clmm::libraries::{liquidity_math, tick_math};
+use swap_io_clmm_rust_sdk::quote::QuoteCalculator;
+
+use common::{GeneratedPool, Position, TRADE_FEE_RATE};
+
+#[test]
+fn distribution_matches_positions() {
+    let pool = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 15,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![Position {
+            tick_lower: -300,
+            tick_upper: 200,
+            liquidity: 2_000_000_000_000,
+        }],
+    };
+    let pool_manager = pool.build();
+    let distribution = pool_manager.get_liquidity_distribution().unwrap();
+    let ranges = &distribution.ranges;
+
+    for pair in ranges.windows(2) {
+        assert_eq!(pair[0].tick_upper, pair[1].tick_lower);
+        assert_eq!(pair[0].price_upper, pair[1].price_lower);
+        assert!(pair[0].price_lower < pair[0].price_upper);
+    }
+    let liquidity_at = |tick: i32| {
+        ranges
+            .iter()
+            .find(|range| range.tick_lower <= tick && tick < range.tick_upper)
+            .unwrap()
+            .liquidity
+    };
+    assert_eq!(liquidity_at(15), pool_manager.pool_state.liquidity);
+    assert_eq!(liquidity_at(-300), pool.wide_liquidity + 2_000_000_000_000);
+    assert_eq!(liquidity_at(199), pool.wide_liquidity + 2_000_000_000_000);
+    assert_eq!(liquidity_at(-301), pool.wide_liquidity);
+    assert_eq!(liquidity_at(200), pool.wide_liquidity);
+    assert_eq!(ranges.first().unwrap().liquidity, 0);
+    assert_eq!(ranges.last().unwrap().liquidity, 0);
+
+    // depth grows away from the current price on both sides
+    let depth = &distribution.depth;
+    let current = depth.iter().position(|level| level.tick > 15).unwrap();
+    for pair in depth[..current].windows(2) {
+        assert!(pair[0].cumulative_amount_1 >= pair[1].cumulative_amount_1);
+    }
+    for pair in depth[current..].windows(2) {
+        assert!(pair[0].cumulative_amount_0 <= pair[1].cumulative_amount_0);
+    }
+
+    // buying the depth up to the next boundary costs the mint 1 moving the price there,
+    // plus the trade fee
+    let level = depth[current];
+    let quote = QuoteCalculator::calculate_quote(
+        pool_manager.pool_state.token_mint_1,
+        pool_manager.pool_state.token_mint_0,
+        false,
+        level.cumulative_amount_0 as u64,
+        &pool_manager,
+    )
+    .unwrap();
+    assert_eq!(quote.out_amount as u128, level.cumulative_amount_0);
+    let amount_1 = liquidity_math::get_delta_amount_1_unsigned(
+        pool_manager.pool_state.sqrt_price_x64,
+        tick_math::get_sqrt_price_at_tick(level.tick).unwrap(),
+        pool_manager.pool_state.liquidity,
+        true,
+    )
+    .unwrap();
+    let fee =
+        (amount_1 as u128 * TRADE_FEE_RATE as u128).div_ceil(1_000_000 - TRADE_FEE_RATE as u128);
+    let cost = amount_1 + fee as u64;
+    // the depth is rounded down, so the quote may stop a unit or two short of the boundary
+    assert!(
+        quote.in_amount.abs_diff(cost) <= 3,
+        "in {} cost {}",
+        quote.in_amount,
+        cost
+    );
+}
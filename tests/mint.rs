@@ -0,0 +1,332 @@
+//! Mint capabilities read from Token-2022 extensions.
+
+mod common;
+
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use solana_sdk::{clock::Clock, pubkey::Pubkey};
+use spl_token_2022::{
+    extension::{
+        BaseStateWithExtensions, ExtensionType, StateWithExtensions, StateWithExtensionsMut,
+        default_account_state::DefaultAccountState,
+        interest_bearing_mint::InterestBearingConfig,
+        metadata_pointer::MetadataPointer,
+        mint_close_authority::MintCloseAuthority,
+        non_transferable::NonTransferable,
+        permanent_delegate::PermanentDelegate,
+        transfer_fee::{TransferFee, TransferFeeConfig},
+        transfer_hook::TransferHook,
+    },
+    pod::OptionalNonZeroPubkey,
+    state::{AccountState, Mint},
+};
+use swap_io_clmm_rust_sdk::{
+    mint::{MintCapabilities, TransferFeeSchedule, amount_to_ui_amount, ui_amount_to_amount},
+    pool::{PoolManager, TransferFeeChange},
+    quote::{QuoteCalculator, QuoteConfig},
+    utils::{ExtensionStruct, get_account_extensions},
+};
+
+use common::{GeneratedPool, PoolKeys};
+
+// the year spl-token-2022 compounds interest over
+const SECONDS_PER_YEAR: i64 = 31_556_736;
+
+fn mint_data(
+    extension_types: &[ExtensionType],
+    init: impl FnOnce(&mut StateWithExtensionsMut<Mint>),
+) -> Vec<u8> {
+    common::token_2022_mint_account(extension_types, init).data
+}
+
+fn optional_key(key: Pubkey) -> OptionalNonZeroPubkey {
+    OptionalNonZeroPubkey::try_from(Some(key)).unwrap()
+}
+
+#[test]
+fn plain_mint_is_supported() {
+    let capabilities = MintCapabilities::from_mint_data(&common::mint_account().data, 0).unwrap();
+    assert_eq!(capabilities.decimals, 6);
+    assert!(capabilities.extensions.is_empty());
+    assert!(capabilities.clmm_supported);
+    assert!(!capabilities.has_transfer_fee());
+    assert!(!capabilities.has_risky_extensions());
+}
+
+// 1% up to 1_000 until epoch 10, then 2% up to 2_000
+fn transfer_fee_mint() -> Vec<u8> {
+    mint_data(&[ExtensionType::TransferFeeConfig], |state| {
+        let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        config.older_transfer_fee = TransferFee {
+            epoch: 0.into(),
+            maximum_fee: 1_000.into(),
+            transfer_fee_basis_points: 100.into(),
+        };
+        config.newer_transfer_fee = TransferFee {
+            epoch: 10.into(),
+            maximum_fee: 2_000.into(),
+            transfer_fee_basis_points: 200.into(),
+        };
+    })
+}
+
+#[test]
+fn transfer_fee_schedules() {
+    let data = transfer_fee_mint();
+    let newer = TransferFeeSchedule {
+        epoch: 10,
+        transfer_fee_basis_points: 200,
+        maximum_fee: 2_000,
+    };
+
+    let before = MintCapabilities::from_mint_data(&data, 5).unwrap();
+    assert_eq!(
+        before.transfer_fee,
+        Some(TransferFeeSchedule {
+            epoch: 0,
+            transfer_fee_basis_points: 100,
+            maximum_fee: 1_000,
+        })
+    );
+    assert_eq!(before.scheduled_transfer_fee, Some(newer));
+    assert!(before.has_transfer_fee());
+    assert!(before.clmm_supported);
+    assert!(!before.has_risky_extensions());
+
+    let after = MintCapabilities::from_mint_data(&data, 10).unwrap();
+    assert_eq!(after.transfer_fee, Some(newer));
+    assert_eq!(after.scheduled_transfer_fee, None);
+}
+
+#[test]
+fn epoch_change_switches_to_the_newer_transfer_fee() {
+    let pool = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 0,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    };
+    let keys = PoolKeys::unique();
+    let mint0 = common::program_account(transfer_fee_mint(), spl_token_2022::id());
+    let mut pool_manager = pool.load(
+        &keys,
+        &pool.accounts(&keys),
+        &mint0,
+        &common::mint_account(),
+    );
+    let quote = |pool_manager: &PoolManager| {
+        QuoteCalculator::calculate_quote(
+            keys.token_mint_0,
+            keys.token_mint_1,
+            true,
+            100_000,
+            pool_manager,
+        )
+        .unwrap()
+    };
+
+    // still the older fee
+    assert_eq!(pool_manager.set_epoch(9).unwrap(), vec![]);
+    let older = quote(&pool_manager);
+    // a config epoch overrides the one the manager was left at
+    let at_clock = QuoteCalculator::calculate_quote_with_config(
+        keys.token_mint_0,
+        keys.token_mint_1,
+        true,
+        100_000,
+        &pool_manager,
+        &QuoteConfig::default().with_clock(&Clock {
+            epoch: 10,
+            // after the pool's open time
+            unix_timestamp: 1,
+            ..Clock::default()
+        }),
+    )
+    .unwrap();
+
+    let changes = pool_manager
+        .update_clock(&Clock {
+            epoch: 10,
+            ..Clock::default()
+        })
+        .unwrap();
+    assert_eq!(
+        changes,
+        vec![TransferFeeChange {
+            mint: keys.token_mint_0,
+            old_epoch: 9,
+            new_epoch: 10,
+            old_transfer_fee_basis_points: 100,
+            old_maximum_fee: 1_000,
+            new_transfer_fee_basis_points: 200,
+            new_maximum_fee: 2_000,
+        }]
+    );
+    assert_eq!(pool_manager.epoch, 10);
+    let newer = quote(&pool_manager);
+    assert_eq!(at_clock.out_amount, newer.out_amount);
+    // 1_000 more of the input withheld leaves less to swap
+    assert!(newer.out_amount < older.out_amount);
+    assert!(older.out_amount - newer.out_amount >= 990);
+
+    assert_eq!(pool_manager.set_epoch(11).unwrap(), vec![]);
+}
+
+#[test]
+fn risky_extensions_are_flagged() {
+    let hook_program = Pubkey::new_unique();
+    let delegate = Pubkey::new_unique();
+    let data = mint_data(
+        &[
+            ExtensionType::TransferHook,
+            ExtensionType::PermanentDelegate,
+            ExtensionType::NonTransferable,
+            ExtensionType::DefaultAccountState,
+        ],
+        |state| {
+            state
+                .init_extension::<TransferHook>(true)
+                .unwrap()
+                .program_id = optional_key(hook_program);
+            state
+                .init_extension::<PermanentDelegate>(true)
+                .unwrap()
+                .delegate = optional_key(delegate);
+            state.init_extension::<NonTransferable>(true).unwrap();
+            state
+                .init_extension::<DefaultAccountState>(true)
+                .unwrap()
+                .state = AccountState::Frozen as u8;
+        },
+    );
+    let capabilities = MintCapabilities::from_mint_data(&data, 0).unwrap();
+    assert_eq!(capabilities.transfer_hook_program, Some(hook_program));
+    assert_eq!(capabilities.permanent_delegate, Some(delegate));
+    assert!(capabilities.non_transferable);
+    assert!(capabilities.default_frozen);
+    assert!(!capabilities.confidential_transfers);
+    assert!(capabilities.has_risky_extensions());
+    assert!(!capabilities.clmm_supported);
+}
+
+#[test]
+fn transfer_hook_mint_is_not_supported() {
+    let data = mint_data(&[ExtensionType::TransferHook], |state| {
+        state
+            .init_extension::<TransferHook>(true)
+            .unwrap()
+            .program_id = optional_key(Pubkey::new_unique());
+    });
+    let capabilities = MintCapabilities::from_mint_data(&data, 0).unwrap();
+    assert!(capabilities.transfer_hook_program.is_some());
+    assert!(!capabilities.clmm_supported);
+}
+
+#[test]
+fn unsupported_extension_is_reported() {
+    let data = mint_data(
+        &[
+            ExtensionType::MetadataPointer,
+            ExtensionType::MintCloseAuthority,
+        ],
+        |state| {
+            state.init_extension::<MetadataPointer>(true).unwrap();
+            state.init_extension::<MintCloseAuthority>(true).unwrap();
+        },
+    );
+    let capabilities = MintCapabilities::from_mint_data(&data, 0).unwrap();
+    assert!(!capabilities.clmm_supported);
+    assert!(!capabilities.has_risky_extensions());
+
+    let mint_state = StateWithExtensions::<Mint>::unpack(&data).unwrap();
+    assert_eq!(mint_state.get_extension_types().unwrap().len(), 2);
+    let extensions = get_account_extensions(&mint_state).unwrap();
+    assert!(matches!(
+        extensions[0],
+        ExtensionStruct::Other(ExtensionType::MetadataPointer)
+    ));
+    assert!(matches!(
+        extensions[1],
+        ExtensionStruct::MintCloseAuthority(_)
+    ));
+}
+
+// 5% a year since the epoch
+fn interest_bearing_mint() -> Vec<u8> {
+    mint_data(&[ExtensionType::InterestBearingConfig], |state| {
+        let config = state.init_extension::<InterestBearingConfig>(true).unwrap();
+        config.pre_update_average_rate = 500.into();
+        config.current_rate = 500.into();
+    })
+}
+
+#[test]
+fn ui_amounts_of_plain_mints_shift_the_decimals() {
+    let data = common::mint_account().data;
+    let ui_amount = amount_to_ui_amount(&data, 1_234_567, 0).unwrap();
+    assert_eq!(ui_amount, Decimal::new(1_234_567, 6));
+    assert_eq!(ui_amount_to_amount(&data, ui_amount, 0).unwrap(), 1_234_567);
+    assert_eq!(
+        ui_amount_to_amount(&data, Decimal::new(1_2345678, 7), 0).unwrap(),
+        1_234_567
+    );
+}
+
+#[test]
+fn ui_amounts_accrue_interest() {
+    let data = interest_bearing_mint();
+    let amount = 1_000_000_000_000;
+    assert_eq!(
+        amount_to_ui_amount(&data, amount, 0).unwrap(),
+        Decimal::new(1000, 0)
+    );
+    let ui_amount = amount_to_ui_amount(&data, amount, SECONDS_PER_YEAR).unwrap();
+    let expected = 1000.0 * 0.05f64.exp();
+    assert!((ui_amount.to_f64().unwrap() - expected).abs() < 1e-6);
+    let back = ui_amount_to_amount(&data, ui_amount, SECONDS_PER_YEAR).unwrap();
+    assert!(back.abs_diff(amount) <= 1);
+}
+
+#[test]
+fn quotes_carry_ui_amounts_at_the_timestamp() {
+    let pool = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 0,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    };
+    let keys = PoolKeys::unique();
+    let accounts = pool.accounts(&keys);
+    let mint0 = common::program_account(interest_bearing_mint(), spl_token_2022::id());
+    let pool_manager = pool.load(&keys, &accounts, &mint0, &common::mint_account());
+    let quote = |config: &QuoteConfig| {
+        QuoteCalculator::calculate_quote_with_config(
+            keys.token_mint_0,
+            keys.token_mint_1,
+            true,
+            1_000_000_000,
+            &pool_manager,
+            config,
+        )
+        .unwrap()
+    };
+
+    let without_timestamp = quote(&QuoteConfig::default());
+    assert_eq!(without_timestamp.ui_in_amount, None);
+    assert_eq!(without_timestamp.ui_out_amount, None);
+
+    let config = QuoteConfig {
+        ui_amount_timestamp: Some(SECONDS_PER_YEAR),
+        ..QuoteConfig::default()
+    };
+    let with_timestamp = quote(&config);
+    assert_eq!(with_timestamp.in_amount, without_timestamp.in_amount);
+    assert_eq!(
+        with_timestamp.ui_in_amount,
+        Some(amount_to_ui_amount(&mint0.data, with_timestamp.in_amount, SECONDS_PER_YEAR).unwrap())
+    );
+    assert!(with_timestamp.ui_in_amount.unwrap() > Decimal::ONE);
+    assert_eq!(
+        with_timestamp.ui_out_amount,
+        Some(Decimal::new(with_timestamp.out_amount as i64, 6))
+    );
+}
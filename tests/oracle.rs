@@ -0,0 +1,98 @@
+//! TWAP over the observations of a pool.
+
+mod common;
+
+use rust_decimal::Decimal;
+use swap_io_clmm::states::{OBSERVATION_NUM, ObservationState};
+use swap_io_clmm_rust_sdk::{
+    oracle::{observations, twap},
+    utils::tick_to_decimal_price,
+};
+
+use common::GeneratedPool;
+
+// ticks held between consecutive observations, as (timestamp, tick held since the
+// previous observation)
+fn observation_state(start: u32, segments: &[(u32, i32)], first_index: usize) -> ObservationState {
+    let mut observation_state: ObservationState = bytemuck::Zeroable::zeroed();
+    let mut index = first_index;
+    let mut timestamp = start;
+    let mut tick_cumulative = 0i64;
+    observation_state.observations[index].block_timestamp = timestamp;
+    for (next_timestamp, tick) in segments {
+        tick_cumulative += *tick as i64 * (next_timestamp - timestamp) as i64;
+        timestamp = *next_timestamp;
+        index = (index + 1) % OBSERVATION_NUM;
+        observation_state.observations[index].block_timestamp = timestamp;
+        observation_state.observations[index].tick_cumulative = tick_cumulative;
+    }
+    observation_state.observation_index = index as u16;
+    observation_state.initialized = true;
+    observation_state
+}
+
+fn pool(tick_current: i32) -> GeneratedPool {
+    GeneratedPool {
+        tick_spacing: 10,
+        tick_current,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    }
+}
+
+#[test]
+fn twap_weights_ticks_by_time() {
+    let pool_manager = pool(300).build();
+    let mut observation_state =
+        observation_state(1_000, &[(1_100, 100), (1_200, 200)], OBSERVATION_NUM - 1);
+    observation_state.pool_id = pool_manager.pool_key;
+    assert_eq!(observations(&observation_state).len(), 3);
+
+    // 100s at tick 100, 100s at tick 200, 100s at the current tick 300
+    let twap = pool_manager.twap(&observation_state, 1_300, 300).unwrap();
+    assert_eq!(twap.average_tick, 200);
+    assert!(twap.is_fully_covered());
+    assert_eq!(twap.coverage(), Decimal::ONE);
+    let pool_state = &pool_manager.pool_state;
+    assert_eq!(
+        twap.price,
+        tick_to_decimal_price(200, pool_state.mint_decimals_0, pool_state.mint_decimals_1).unwrap()
+    );
+
+    // starting halfway into the first segment: 50s at 100, 100s at 200
+    let twap = pool_manager.twap(&observation_state, 1_200, 150).unwrap();
+    assert_eq!(twap.start_timestamp, 1_050);
+    assert_eq!(twap.average_tick, (50 * 100 + 100 * 200) / 150);
+}
+
+#[test]
+fn twap_reports_partial_coverage() {
+    let pool_manager = pool(-10).build();
+    let mut observation_state = observation_state(1_000, &[(1_100, -5)], 0);
+    observation_state.pool_id = pool_manager.pool_key;
+
+    let twap = pool_manager.twap(&observation_state, 1_100, 400).unwrap();
+    assert_eq!(twap.start_timestamp, 1_000);
+    assert_eq!(twap.covered_seconds, 100);
+    assert_eq!(twap.coverage(), Decimal::new(25, 2));
+    assert!(!twap.is_fully_covered());
+    assert_eq!(twap.average_tick, -5);
+
+    // 100s at -5 and 1s at -10 round towards negative infinity
+    let twap = pool_manager.twap(&observation_state, 1_101, 101).unwrap();
+    assert_eq!(twap.average_tick, -6);
+}
+
+#[test]
+fn twap_rejects_unusable_inputs() {
+    let pool_manager = pool(0).build();
+    let mut observation_state = observation_state(1_000, &[(1_100, 0)], 0);
+    // observations of another pool
+    assert!(pool_manager.twap(&observation_state, 1_200, 100).is_err());
+
+    observation_state.pool_id = pool_manager.pool_key;
+    assert!(pool_manager.twap(&observation_state, 1_200, 0).is_err());
+    assert!(pool_manager.twap(&observation_state, 1_050, 100).is_err());
+    let empty: ObservationState = bytemuck::Zeroable::zeroed();
+    assert!(twap(&empty, &pool_manager.pool_state, 1_000, 100).is_err());
+}
@@ -0,0 +1,163 @@
+//! Pool status bits and open time checked before quoting and building swaps.
+
+mod common;
+
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm::states::PoolStatusBitIndex;
+use swap_io_clmm_rust_sdk::{
+    instruction::InstructionBuilder,
+    pool::{PoolManager, PoolStatus},
+    quote::{QuoteCalculator, QuoteConfig},
+    transaction::{SwapTransactionBuilder, SwapTransactionOptions},
+};
+
+use common::GeneratedPool;
+
+fn pool_manager() -> PoolManager {
+    GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 0,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    }
+    .build()
+}
+
+fn quote_at(pool_manager: &PoolManager, unix_timestamp: Option<i64>) -> anyhow::Result<()> {
+    let pool_state = &pool_manager.pool_state;
+    QuoteCalculator::calculate_quote_with_config(
+        pool_state.token_mint_0,
+        pool_state.token_mint_1,
+        true,
+        1_000_000,
+        pool_manager,
+        &QuoteConfig {
+            open_time_check: unix_timestamp,
+            ..QuoteConfig::default()
+        },
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn status_bits_disable_operations() {
+    let mut pool_manager = pool_manager();
+    assert_eq!(
+        pool_manager.status(),
+        PoolStatus {
+            open_position: true,
+            decrease_liquidity: true,
+            collect_fee: true,
+            collect_reward: true,
+            swap: true,
+        }
+    );
+
+    pool_manager.pool_state.status =
+        1 << PoolStatusBitIndex::Swap as u8 | 1 << PoolStatusBitIndex::CollectReward as u8;
+    let status = pool_manager.status();
+    assert!(!status.swap);
+    assert!(!status.collect_reward);
+    assert!(status.open_position && status.decrease_liquidity && status.collect_fee);
+}
+
+#[test]
+fn disabled_swaps_are_neither_quoted_nor_built() {
+    let mut pool_manager = pool_manager();
+    assert!(quote_at(&pool_manager, None).is_ok());
+
+    pool_manager.pool_state.status = 1 << PoolStatusBitIndex::Swap as u8;
+    let err = quote_at(&pool_manager, None).unwrap_err();
+    assert!(err.to_string().contains("Swaps are disabled"));
+
+    let pool_state = &pool_manager.pool_state;
+    assert!(
+        InstructionBuilder::build_swap_instruction(
+            &pool_manager,
+            pool_state.token_mint_0,
+            pool_state.token_mint_1,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        )
+        .is_err()
+    );
+
+    // other operations disabled leave swaps alone
+    pool_manager.pool_state.status = 1 << PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity as u8;
+    assert!(quote_at(&pool_manager, None).is_ok());
+}
+
+#[test]
+fn pools_open_strictly_after_open_time() {
+    let mut pool_manager = pool_manager();
+    pool_manager.pool_state.open_time = 1_000;
+
+    // the open time needs a timestamp to be checked
+    assert!(quote_at(&pool_manager, None).is_ok());
+    assert!(quote_at(&pool_manager, Some(999)).is_err());
+    assert!(quote_at(&pool_manager, Some(1_000)).is_err());
+    assert!(quote_at(&pool_manager, Some(1_001)).is_ok());
+
+    // UI amounts can be converted at any time without checking the open time
+    let pool_state = &pool_manager.pool_state;
+    let quote = QuoteCalculator::calculate_quote_with_config(
+        pool_state.token_mint_0,
+        pool_state.token_mint_1,
+        true,
+        1_000_000,
+        &pool_manager,
+        &QuoteConfig {
+            ui_amount_timestamp: Some(999),
+            ..QuoteConfig::default()
+        },
+    )
+    .unwrap();
+    assert!(quote.ui_in_amount.is_some());
+
+    let pool_state = &pool_manager.pool_state;
+    let quote = QuoteCalculator::calculate_quote(
+        pool_state.token_mint_0,
+        pool_state.token_mint_1,
+        true,
+        1_000_000,
+        &pool_manager,
+    )
+    .unwrap();
+    let builder = |unix_timestamp: Option<i64>| {
+        SwapTransactionBuilder::new(
+            &pool_manager,
+            Pubkey::new_unique(),
+            pool_state.token_mint_0,
+            pool_state.token_mint_1,
+            true,
+            quote,
+        )
+        .options(SwapTransactionOptions {
+            unix_timestamp,
+            ..SwapTransactionOptions::default()
+        })
+    };
+    let err = builder(Some(500)).build_instructions().unwrap_err();
+    assert!(err.to_string().contains("opens at 1000"));
+    assert!(builder(Some(2_000)).build_instructions().is_ok());
+
+    let build_swap_v2 = |unix_timestamp: Option<i64>| {
+        InstructionBuilder::build_swap_v2_instruction_at(
+            &pool_manager,
+            Pubkey::new_unique(),
+            pool_state.token_mint_0,
+            pool_state.token_mint_1,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            1_000_000,
+            0,
+            None,
+            true,
+            unix_timestamp,
+        )
+    };
+    let err = build_swap_v2(Some(1_000)).unwrap_err();
+    assert!(err.to_string().contains("opens at 1000"));
+    assert!(build_swap_v2(Some(1_001)).is_ok());
+    assert!(build_swap_v2(None).is_ok());
+}
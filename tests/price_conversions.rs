@@ -0,0 +1,103 @@
+//! Exact tick and price conversions against the program's tick math.
+
+use proptest::prelude::*;
+use rust_decimal::Decimal;
+use swap_io_clmm::libraries::tick_math;
+use swap_io_clmm_rust_sdk::utils::{
+    TickRounding, decimal_price_to_sqrt_price_x64, decimal_price_to_tick, multipler,
+    nearest_usable_tick, price_to_tick, sqrt_price_x64_to_decimal_price, tick_to_decimal_price,
+    tick_with_spacing, usable_tick,
+};
+
+#[test]
+fn price_of_tick_zero_is_the_decimals_ratio() {
+    assert_eq!(tick_to_decimal_price(0, 6, 6).unwrap(), Decimal::ONE);
+    assert_eq!(
+        tick_to_decimal_price(0, 9, 6).unwrap(),
+        Decimal::new(1000, 0)
+    );
+    assert_eq!(tick_to_decimal_price(0, 6, 9).unwrap(), Decimal::new(1, 3));
+    assert_eq!(decimal_price_to_tick(Decimal::ONE, 6, 6).unwrap(), 0);
+    assert_eq!(
+        decimal_price_to_sqrt_price_x64(Decimal::ONE, 6, 6).unwrap(),
+        1u128 << 64
+    );
+}
+
+#[test]
+fn ticks_round_down() {
+    // 1.0001^-1 < 0.99995 < 1
+    assert_eq!(
+        decimal_price_to_tick(Decimal::new(99995, 5), 0, 0).unwrap(),
+        -1
+    );
+    assert_eq!(price_to_tick(0.99995), -1);
+    assert_eq!(
+        decimal_price_to_tick(Decimal::new(100005, 5), 0, 0).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn out_of_range_prices_are_rejected() {
+    assert!(decimal_price_to_tick(Decimal::ZERO, 6, 6).is_err());
+    assert!(decimal_price_to_tick(Decimal::new(-1, 0), 6, 6).is_err());
+    assert!(decimal_price_to_tick(Decimal::MAX, 0, 0).is_err());
+    assert!(tick_to_decimal_price(tick_math::MAX_TICK + 1, 6, 6).is_err());
+    assert!(tick_to_decimal_price(0, 40, 0).is_err());
+}
+
+#[test]
+fn multipler_covers_large_decimals() {
+    assert_eq!(multipler(9), 1e9);
+    assert_eq!(multipler(18), 1e18);
+}
+
+#[test]
+fn usable_ticks_follow_the_rounding() {
+    assert_eq!(usable_tick(-15, 10, TickRounding::Down), -20);
+    assert_eq!(usable_tick(-15, 10, TickRounding::Up), -10);
+    assert_eq!(usable_tick(-15, 10, TickRounding::Nearest), -10);
+    assert_eq!(usable_tick(-16, 10, TickRounding::Nearest), -20);
+    assert_eq!(usable_tick(14, 10, TickRounding::Nearest), 10);
+    assert_eq!(usable_tick(20, 10, TickRounding::Up), 20);
+    assert_eq!(nearest_usable_tick(tick_math::MAX_TICK, 60), 443580);
+    assert_eq!(nearest_usable_tick(tick_math::MIN_TICK, 60), -443580);
+}
+
+proptest! {
+    #[test]
+    fn tick_price_round_trip(
+        tick in tick_math::MIN_TICK..tick_math::MAX_TICK,
+        decimals_0 in 0u8..=12,
+        decimals_1 in 0u8..=12,
+    ) {
+        let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(tick).unwrap();
+        let price = sqrt_price_x64_to_decimal_price(sqrt_price_x64, decimals_0, decimals_1);
+        // the extremes of the range leave Decimal's range or precision
+        prop_assume!(price.is_ok());
+        let price = price.unwrap();
+        prop_assume!(!price.is_zero());
+        let back = decimal_price_to_sqrt_price_x64(price, decimals_0, decimals_1).unwrap();
+        prop_assert!(back <= sqrt_price_x64);
+        if price.mantissa() >= 10i128.pow(18) {
+            // rounding the price down can only drop it below the tick's own price
+            let floor_tick = decimal_price_to_tick(price, decimals_0, decimals_1).unwrap();
+            prop_assert!(floor_tick == tick || floor_tick == tick - 1);
+        }
+    }
+
+    #[test]
+    fn usable_ticks_are_spaced_and_in_range(
+        tick in tick_math::MIN_TICK..=tick_math::MAX_TICK,
+        tick_spacing in 1u16..=200,
+    ) {
+        for rounding in [TickRounding::Down, TickRounding::Up, TickRounding::Nearest] {
+            let usable = usable_tick(tick, tick_spacing, rounding);
+            prop_assert_eq!(usable % tick_spacing as i32, 0);
+            prop_assert!((tick_math::MIN_TICK..=tick_math::MAX_TICK).contains(&usable));
+            let max_usable = tick_with_spacing(tick_math::MAX_TICK, tick_spacing as i32);
+            prop_assert!((usable - tick).abs() < tick_spacing as i32 || usable.abs() == max_usable);
+        }
+    }
+}
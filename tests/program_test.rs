@@ -0,0 +1,590 @@
+//! Executes swaps built by `InstructionBuilder` against the real program and
+//! checks the token balance changes against `QuoteCalculator`.
+//!
+//! The program binary is read from `SWAP_IO_CLMM_PROGRAM`, defaulting to
+//! `tests/fixtures/swap_io_clmm.so`, which CI builds from the swap-io-clmm revision
+//! in Cargo.lock (see `.github/workflows/ci.yml`). The test fails without it.
+//! Mints and user accounts are created through the token programs, the pool and
+//! its positions through the program's `create_pool` and
+//! `open_position_with_token22_nft`. Only the `AmmConfig` is written into the bank,
+//! creating one takes the program's admin key.
+
+mod common;
+
+use std::path::PathBuf;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    bpf_loader,
+    clock::Clock,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::Instruction,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program, sysvar,
+    transaction::Transaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+};
+use spl_token_2022::{
+    extension::{
+        ExtensionType, StateWithExtensions,
+        transfer_fee::instruction::initialize_transfer_fee_config,
+    },
+    state::{Account as TokenAccount, Mint},
+};
+use swap_io_clmm::{
+    libraries::tick_math,
+    states::{POOL_VAULT_SEED, POSITION_SEED, TickArrayState},
+};
+use swap_io_clmm_rust_sdk::{
+    instruction::InstructionBuilder, pool::PoolManager, quote::QuoteCalculator,
+};
+
+use common::{GeneratedPool, PoolKeys, Position};
+
+const TRANSFER_FEE_BASIS_POINTS: u16 = 100;
+const MAXIMUM_TRANSFER_FEE: u64 = 1_000_000_000;
+// enough to deposit the generated positions and still swap
+const USER_BALANCE: u64 = 1_000_000_000_000_000;
+
+struct TestPool {
+    context: ProgramTestContext,
+    keys: PoolKeys,
+    // mint_0 is a plain spl-token mint, mint_1 a Token-2022 mint with a transfer fee
+    token_programs: [Pubkey; 2],
+    user_token_accounts: [Pubkey; 2],
+}
+
+fn program_path() -> PathBuf {
+    std::env::var("SWAP_IO_CLMM_PROGRAM")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/swap_io_clmm.so")
+        })
+}
+
+async fn try_process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) {
+    try_process(context, instructions, signers).await.unwrap();
+}
+
+async fn get_account(context: &mut ProgramTestContext, key: Pubkey) -> Account {
+    context
+        .banks_client
+        .get_account(key)
+        .await
+        .unwrap()
+        .unwrap_or_else(|| panic!("account {} not found", key))
+}
+
+async fn token_balance(context: &mut ProgramTestContext, key: Pubkey) -> u64 {
+    let account = get_account(context, key).await;
+    StateWithExtensions::<TokenAccount>::unpack(&account.data)
+        .unwrap()
+        .base
+        .amount
+}
+
+async fn create_mint(
+    context: &mut ProgramTestContext,
+    mint: &Keypair,
+    token_program: Pubkey,
+    extension: Option<ExtensionType>,
+) {
+    let payer = context.payer.pubkey();
+    let space = match extension {
+        Some(extension) => ExtensionType::try_calculate_account_len::<Mint>(&[extension]).unwrap(),
+        None => Mint::LEN,
+    };
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut instructions = vec![system_instruction::create_account(
+        &payer,
+        &mint.pubkey(),
+        rent.minimum_balance(space),
+        space as u64,
+        &token_program,
+    )];
+    match extension {
+        Some(ExtensionType::TransferFeeConfig) => instructions.push(
+            initialize_transfer_fee_config(
+                &token_program,
+                &mint.pubkey(),
+                Some(&payer),
+                Some(&payer),
+                TRANSFER_FEE_BASIS_POINTS,
+                MAXIMUM_TRANSFER_FEE,
+            )
+            .unwrap(),
+        ),
+        Some(extension) => panic!("no initialization for {:?}", extension),
+        None => {}
+    }
+    instructions.push(
+        spl_token_2022::instruction::initialize_mint2(
+            &token_program,
+            &mint.pubkey(),
+            &payer,
+            None,
+            6,
+        )
+        .unwrap(),
+    );
+    process(context, &instructions, &[mint]).await;
+}
+
+async fn create_funded_token_account(
+    context: &mut ProgramTestContext,
+    wallet: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+    amount: u64,
+) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let token_account =
+        get_associated_token_address_with_program_id(&wallet, &mint, &token_program);
+    process(
+        context,
+        &[
+            create_associated_token_account(&payer, &wallet, &mint, &token_program),
+            spl_token_2022::instruction::mint_to(
+                &token_program,
+                &mint,
+                &token_account,
+                &payer,
+                &[],
+                amount,
+            )
+            .unwrap(),
+        ],
+        &[],
+    )
+    .await;
+    token_account
+}
+
+fn program_data() -> Vec<u8> {
+    let program_path = program_path();
+    std::fs::read(&program_path).unwrap_or_else(|err| {
+        panic!(
+            "{} not readable ({}), build swap-io-clmm with `cargo build-sbf` and copy \
+             target/deploy/swap_io_clmm.so there or point SWAP_IO_CLMM_PROGRAM at it",
+            program_path.display(),
+            err
+        )
+    })
+}
+
+fn program_address(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &swap_io_clmm::id()).0
+}
+
+// the pool's keys, vaults included, for two sorted mints
+fn pool_keys(mint_0: Pubkey, mint_1: Pubkey) -> PoolKeys {
+    let pool_key = PoolKeys::new(mint_0, mint_1, Pubkey::default(), Pubkey::default()).pool_key;
+    let vault = |mint: &Pubkey| {
+        program_address(&[POOL_VAULT_SEED.as_bytes(), pool_key.as_ref(), mint.as_ref()])
+    };
+    PoolKeys::new(mint_0, mint_1, vault(&mint_0), vault(&mint_1))
+}
+
+fn create_pool_instruction(
+    context: &ProgramTestContext,
+    keys: &PoolKeys,
+    token_programs: [Pubkey; 2],
+    pool: &GeneratedPool,
+) -> Instruction {
+    Instruction {
+        program_id: keys.program_id,
+        accounts: swap_io_clmm::accounts::CreatePool {
+            pool_creator: context.payer.pubkey(),
+            amm_config: keys.amm_config,
+            pool_state: keys.pool_key,
+            token_mint_0: keys.token_mint_0,
+            token_mint_1: keys.token_mint_1,
+            token_vault_0: keys.token_vault_0,
+            token_vault_1: keys.token_vault_1,
+            observation_state: keys.observation_key,
+            tick_array_bitmap: keys.tickarray_bitmap_extension(),
+            token_program_0: token_programs[0],
+            token_program_1: token_programs[1],
+            system_program: system_program::id(),
+            rent: sysvar::rent::id(),
+        }
+        .to_account_metas(None),
+        data: swap_io_clmm::instruction::CreatePool {
+            sqrt_price_x64: tick_math::get_sqrt_price_at_tick(pool.tick_current).unwrap(),
+            open_time: 0,
+        }
+        .data(),
+    }
+}
+
+async fn open_position(
+    context: &mut ProgramTestContext,
+    keys: &PoolKeys,
+    tick_spacing: u16,
+    user_token_accounts: [Pubkey; 2],
+    position: &Position,
+) {
+    let payer = context.payer.pubkey();
+    let nft_mint = Keypair::new();
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(position.tick_lower, tick_spacing);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(position.tick_upper, tick_spacing);
+    let instruction = Instruction {
+        program_id: keys.program_id,
+        accounts: swap_io_clmm::accounts::OpenPositionWithToken22Nft {
+            payer,
+            position_nft_owner: payer,
+            position_nft_mint: nft_mint.pubkey(),
+            position_nft_account: get_associated_token_address_with_program_id(
+                &payer,
+                &nft_mint.pubkey(),
+                &spl_token_2022::id(),
+            ),
+            pool_state: keys.pool_key,
+            protocol_position: program_address(&[
+                POSITION_SEED.as_bytes(),
+                keys.pool_key.as_ref(),
+                &position.tick_lower.to_be_bytes(),
+                &position.tick_upper.to_be_bytes(),
+            ]),
+            tick_array_lower: keys.tick_array(tick_array_lower_start_index),
+            tick_array_upper: keys.tick_array(tick_array_upper_start_index),
+            personal_position: program_address(&[
+                POSITION_SEED.as_bytes(),
+                nft_mint.pubkey().as_ref(),
+            ]),
+            token_account_0: user_token_accounts[0],
+            token_account_1: user_token_accounts[1],
+            token_vault_0: keys.token_vault_0,
+            token_vault_1: keys.token_vault_1,
+            rent: sysvar::rent::id(),
+            system_program: system_program::id(),
+            token_program: spl_token::id(),
+            associated_token_program: spl_associated_token_account::id(),
+            token_program_2022: spl_token_2022::id(),
+            vault_0_mint: keys.token_mint_0,
+            vault_1_mint: keys.token_mint_1,
+        }
+        .to_account_metas(None),
+        data: swap_io_clmm::instruction::OpenPositionWithToken22Nft {
+            tick_lower_index: position.tick_lower,
+            tick_upper_index: position.tick_upper,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            liquidity: position.liquidity,
+            amount_0_max: u64::MAX,
+            amount_1_max: u64::MAX,
+            with_metadata: false,
+            base_flag: None,
+        }
+        .data(),
+    };
+    process(
+        context,
+        &[
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            instruction,
+        ],
+        &[&nft_mint],
+    )
+    .await;
+}
+
+// a bank with the program and the config `pool` uses
+async fn start(pool: &GeneratedPool) -> ProgramTestContext {
+    let mut program_test = ProgramTest::default();
+    program_test.add_account(
+        swap_io_clmm::id(),
+        Account {
+            lamports: 1_000_000_000,
+            data: program_data(),
+            owner: bpf_loader::id(),
+            executable: true,
+            rent_epoch: 0,
+        },
+    );
+    // the config the generated pool would use, its address does not depend on the mints
+    let unique_keys = PoolKeys::unique();
+    program_test.add_account(
+        unique_keys.amm_config,
+        pool.accounts(&unique_keys).amm_config,
+    );
+    program_test.start_with_context().await
+}
+
+async fn setup() -> TestPool {
+    let pool = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 15,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![
+            Position {
+                tick_lower: -300,
+                tick_upper: 200,
+                liquidity: 2_000_000_000_000,
+            },
+            Position {
+                tick_lower: 20,
+                tick_upper: 900,
+                liquidity: 5_000_000_000_000,
+            },
+        ],
+    };
+    let mut context = start(&pool).await;
+
+    let mut mints = [Keypair::new(), Keypair::new()];
+    mints.sort_by_key(|mint| mint.pubkey());
+    let token_programs = [spl_token::id(), spl_token_2022::id()];
+    create_mint(&mut context, &mints[0], token_programs[0], None).await;
+    create_mint(
+        &mut context,
+        &mints[1],
+        token_programs[1],
+        Some(ExtensionType::TransferFeeConfig),
+    )
+    .await;
+    let (mint_0, mint_1) = (mints[0].pubkey(), mints[1].pubkey());
+    let keys = pool_keys(mint_0, mint_1);
+    let user = context.payer.pubkey();
+    let user_token_accounts = [
+        create_funded_token_account(&mut context, user, mint_0, token_programs[0], USER_BALANCE)
+            .await,
+        create_funded_token_account(&mut context, user, mint_1, token_programs[1], USER_BALANCE)
+            .await,
+    ];
+
+    let create_pool = create_pool_instruction(&context, &keys, token_programs, &pool);
+    process(&mut context, &[create_pool], &[]).await;
+    for position in pool.all_positions() {
+        open_position(
+            &mut context,
+            &keys,
+            pool.tick_spacing,
+            user_token_accounts,
+            &position,
+        )
+        .await;
+    }
+    TestPool {
+        context,
+        keys,
+        token_programs,
+        user_token_accounts,
+    }
+}
+
+impl TestPool {
+    async fn get_optional_account(&mut self, key: Pubkey) -> Option<Account> {
+        self.context.banks_client.get_account(key).await.unwrap()
+    }
+
+    // reload everything the quote depends on, the previous swap moved the pool
+    async fn pool_manager(&mut self) -> PoolManager {
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        let pool_state = get_account(&mut self.context, self.keys.pool_key).await;
+        let mut pool_manager = PoolManager::new(
+            clock.epoch,
+            self.keys.pool_key,
+            self.keys.program_id,
+            &pool_state,
+        )
+        .unwrap();
+        let amm_config = get_account(&mut self.context, self.keys.amm_config).await;
+        let mint0 = get_account(&mut self.context, self.keys.token_mint_0).await;
+        let mint1 = get_account(&mut self.context, self.keys.token_mint_1).await;
+        let bitmap_extension =
+            get_account(&mut self.context, self.keys.tickarray_bitmap_extension()).await;
+        // arrays the program never initialized do not exist
+        let mut up_ticks = vec![];
+        for key in pool_manager.up_tick_array_keys.clone() {
+            up_ticks.extend(self.get_optional_account(key).await);
+        }
+        let mut down_ticks = vec![];
+        for key in pool_manager.down_tick_array_keys.clone() {
+            down_ticks.extend(self.get_optional_account(key).await);
+        }
+        pool_manager
+            .update(
+                vec![&amm_config, &mint0, &mint1, &bitmap_extension],
+                up_ticks,
+                down_ticks,
+            )
+            .unwrap();
+        let vault_0 = get_account(&mut self.context, self.keys.token_vault_0).await;
+        let vault_1 = get_account(&mut self.context, self.keys.token_vault_1).await;
+        pool_manager.update_vaults(&vault_0, &vault_1).unwrap();
+        pool_manager
+    }
+
+    /// Execute a swap built from `pool_manager` and return the amounts that left and
+    /// reached the user's token accounts.
+    async fn swap(
+        &mut self,
+        pool_manager: &PoolManager,
+        zero_for_one: bool,
+        base_in: bool,
+        amount: u64,
+        other_amount_threshold: u64,
+    ) -> (u64, u64) {
+        let (input, output) = if zero_for_one { (0, 1) } else { (1, 0) };
+        let mints = [self.keys.token_mint_0, self.keys.token_mint_1];
+        let instruction = InstructionBuilder::build_swap_v2_instruction(
+            pool_manager,
+            self.context.payer.pubkey(),
+            mints[input],
+            mints[output],
+            self.user_token_accounts[input],
+            self.user_token_accounts[output],
+            amount,
+            other_amount_threshold,
+            None,
+            base_in,
+        )
+        .unwrap();
+
+        let input_before = token_balance(&mut self.context, self.user_token_accounts[input]).await;
+        let output_before =
+            token_balance(&mut self.context, self.user_token_accounts[output]).await;
+        process(&mut self.context, &[instruction], &[]).await;
+        let input_after = token_balance(&mut self.context, self.user_token_accounts[input]).await;
+        let output_after = token_balance(&mut self.context, self.user_token_accounts[output]).await;
+        (input_before - input_after, output_after - output_before)
+    }
+
+    async fn swap_and_compare(&mut self, zero_for_one: bool, base_in: bool, amount: u64) {
+        let pool_manager = self.pool_manager().await;
+        let (input, output) = if zero_for_one { (0, 1) } else { (1, 0) };
+        let mints = [self.keys.token_mint_0, self.keys.token_mint_1];
+        let quote = QuoteCalculator::calculate_quote(
+            mints[input],
+            mints[output],
+            base_in,
+            amount,
+            &pool_manager,
+        )
+        .unwrap();
+        let other_amount_threshold = if base_in {
+            quote.out_amount
+        } else {
+            quote.in_amount
+        };
+        let (in_amount, out_amount) = self
+            .swap(
+                &pool_manager,
+                zero_for_one,
+                base_in,
+                amount,
+                other_amount_threshold,
+            )
+            .await;
+
+        let label = format!(
+            "zero_for_one {} base_in {} amount {} input program {}",
+            zero_for_one, base_in, amount, self.token_programs[input]
+        );
+        assert_eq!(in_amount, quote.in_amount, "{}", label);
+        assert_eq!(out_amount, quote.out_amount, "{}", label);
+    }
+}
+
+// mint_1 charges a transfer fee, so these cover the fee on the input and on the output side
+const SWAPS: [(bool, bool, u64); 6] = [
+    (true, true, 1_000_000),
+    (false, true, 1_000_000),
+    (true, false, 500_000),
+    (false, false, 500_000),
+    (true, true, 3_000_000_000),
+    (false, true, 3_000_000_000),
+];
+
+#[tokio::test]
+async fn quotes_match_program_execution() {
+    let mut test_pool = setup().await;
+    for (zero_for_one, base_in, amount) in SWAPS {
+        test_pool
+            .swap_and_compare(zero_for_one, base_in, amount)
+            .await;
+    }
+}
+
+/// Writes `tests/fixtures/golden/program_test.json` for `golden_quotes.rs`: a snapshot
+/// of the pool and the swaps the program executed from exactly that state.
+///
+/// `cargo test --test program_test -- --ignored record_golden_fixture`
+#[tokio::test]
+#[ignore]
+async fn record_golden_fixture() {
+    let mut test_pool = setup().await;
+    let pool_manager = test_pool.pool_manager().await;
+    let mut saved = vec![];
+    for key in pool_manager.get_loaded_account_keys() {
+        saved.push((key, get_account(&mut test_pool.context, key).await));
+    }
+    let mints = [test_pool.keys.token_mint_0, test_pool.keys.token_mint_1];
+    let mut cases = vec![];
+    for (zero_for_one, base_in, amount) in SWAPS {
+        let other_amount_threshold = if base_in { 0 } else { u64::MAX };
+        let (in_amount, out_amount) = test_pool
+            .swap(
+                &pool_manager,
+                zero_for_one,
+                base_in,
+                amount,
+                other_amount_threshold,
+            )
+            .await;
+        let (input, output) = if zero_for_one { (0, 1) } else { (1, 0) };
+        // the fee is not visible in the balances, only in the pool's fee growth
+        cases.push(serde_json::json!({
+            "input_mint": mints[input].to_string(),
+            "output_mint": mints[output].to_string(),
+            "base_in": base_in,
+            "amount": amount,
+            "in_amount": in_amount,
+            "out_amount": out_amount,
+        }));
+        // every case starts from the snapshotted state
+        for (key, account) in &saved {
+            test_pool.context.set_account(key, &account.clone().into());
+        }
+    }
+
+    let snapshot: serde_json::Value =
+        serde_json::from_str(&pool_manager.to_snapshot().unwrap().to_json().unwrap()).unwrap();
+    let fixture = serde_json::json!({
+        "description": "program-test pool, tick spacing 10, mint 1 with a 1% transfer fee",
+        "snapshot": snapshot,
+        "cases": cases,
+    });
+    let path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden/program_test.json");
+    std::fs::write(&path, serde_json::to_string_pretty(&fixture).unwrap()).unwrap();
+}
@@ -0,0 +1,185 @@
+//! Pools refreshed over rpc and quoted on the best pool for a pair.
+
+mod common;
+
+use std::collections::HashMap;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use solana_client::{rpc_client::RpcClient, rpc_request::RpcRequest};
+use solana_sdk::{
+    account::{Account, create_account_for_test},
+    clock::Clock,
+    pubkey::Pubkey,
+    sysvar,
+};
+use swap_io_clmm_rust_sdk::{pool::PoolManager, quote::QuoteConfig, registry::PoolRegistry};
+
+use common::{GeneratedPool, PoolAccounts, PoolKeys, mint_account};
+
+fn pool(wide_liquidity: u128) -> GeneratedPool {
+    GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 0,
+        wide_liquidity,
+        positions: vec![],
+    }
+}
+
+fn ui_account(account: &Account) -> serde_json::Value {
+    serde_json::json!({
+        "lamports": account.lamports,
+        "data": [STANDARD.encode(&account.data), "base64"],
+        "owner": account.owner.to_string(),
+        "executable": account.executable,
+        "rentEpoch": account.rent_epoch,
+        "space": account.data.len(),
+    })
+}
+
+// answers the single getMultipleAccounts call of a refresh, in the order it asks
+fn rpc_client(
+    pool_manager: &PoolManager,
+    accounts: &PoolAccounts,
+    slot: u64,
+    epoch: u64,
+) -> RpcClient {
+    let mint = mint_account();
+    let clock = create_account_for_test(&Clock {
+        slot,
+        epoch,
+        ..Clock::default()
+    });
+    let mut keyed: HashMap<Pubkey, &Account> = accounts
+        .tick_arrays
+        .iter()
+        .map(|(key, account)| (*key, account))
+        .collect();
+    keyed.insert(pool_manager.pool_key, &accounts.pool_state);
+    keyed.insert(pool_manager.pool_state.amm_config, &accounts.amm_config);
+    keyed.insert(pool_manager.pool_state.token_mint_0, &mint);
+    keyed.insert(pool_manager.pool_state.token_mint_1, &mint);
+    keyed.insert(
+        pool_manager.tick_array_bitmap_extension(),
+        &accounts.tickarray_bitmap_extension,
+    );
+    keyed.insert(sysvar::clock::id(), &clock);
+
+    let mut keys = vec![
+        pool_manager.pool_key,
+        pool_manager.pool_state.amm_config,
+        pool_manager.pool_state.token_mint_0,
+        pool_manager.pool_state.token_mint_1,
+        pool_manager.tick_array_bitmap_extension(),
+    ];
+    keys.extend(pool_manager.up_tick_array_keys.iter());
+    keys.extend(pool_manager.down_tick_array_keys.iter());
+    keys.push(sysvar::clock::id());
+    let value: Vec<serde_json::Value> = keys
+        .iter()
+        .map(|key| {
+            keyed
+                .get(key)
+                .map_or(serde_json::Value::Null, |account| ui_account(account))
+        })
+        .collect();
+
+    let mut mocks = HashMap::new();
+    mocks.insert(
+        RpcRequest::GetMultipleAccounts,
+        serde_json::json!({ "context": { "slot": slot }, "value": value }),
+    );
+    RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+}
+
+#[test]
+fn refresh_moves_pools_to_the_current_epoch() {
+    let pool = pool(10_000_000_000_000);
+    let keys = PoolKeys::unique();
+    let accounts = pool.accounts(&keys);
+    let pool_manager = pool.load(&keys, &accounts, &mint_account(), &mint_account());
+    assert_eq!(pool_manager.epoch, 0);
+    let rpc_client = rpc_client(&pool_manager, &accounts, 500, 12);
+
+    let mut registry = PoolRegistry::new();
+    registry.insert(pool_manager);
+    assert_eq!(registry.epoch(), None);
+
+    assert!(registry.refresh(&rpc_client).is_empty());
+    let pool_manager = registry.get(&keys.pool_key).unwrap();
+    assert_eq!(pool_manager.epoch, 12);
+    assert_eq!(pool_manager.get_account_slot(&keys.pool_key), Some(500));
+    assert_eq!(
+        pool_manager.get_account_slot(&keys.tickarray_bitmap_extension()),
+        Some(500)
+    );
+    assert_eq!(registry.epoch(), Some(12));
+}
+
+#[test]
+fn failed_refresh_keeps_the_previous_pool() {
+    let pool_manager = pool(10_000_000_000_000).build();
+    let pool_key = pool_manager.pool_key;
+    let mut registry = PoolRegistry::new();
+    registry.insert(pool_manager);
+
+    // no mocked accounts, so the refresh finds nothing
+    let rpc_client = RpcClient::new_mock_with_mocks("succeeds".to_string(), HashMap::new());
+    let errors = registry.refresh(&rpc_client);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, pool_key);
+    assert_eq!(registry.get(&pool_key).unwrap().epoch, 0);
+    assert_eq!(registry.epoch(), None);
+}
+
+#[test]
+fn quotes_pick_the_best_pool_for_the_pair() {
+    // two pools trading the same mints, the second ten times deeper
+    let shallow_keys = PoolKeys::unique();
+    let mint = mint_account();
+    let load =
+        |pool: GeneratedPool, keys: &PoolKeys| pool.load(keys, &pool.accounts(keys), &mint, &mint);
+    let shallow = load(pool(1_000_000_000_000), &shallow_keys);
+    let mut deep = load(pool(10_000_000_000_000), &shallow_keys);
+    deep.pool_key = Pubkey::new_unique();
+    let (shallow_key, deep_key) = (shallow.pool_key, deep.pool_key);
+    let (mint_0, mint_1) = (shallow_keys.token_mint_0, shallow_keys.token_mint_1);
+
+    let mut registry = PoolRegistry::new();
+    registry.insert(shallow);
+    registry.insert(deep);
+    assert_eq!(registry.pools_for_pair(mint_1, mint_0).len(), 2);
+    assert!(registry.pools_for_pair(mint_0, mint_0).is_empty());
+
+    let config = QuoteConfig::default();
+    let amount = 10_000_000_000;
+    let (exact_in_pool, exact_in) = registry
+        .quote(None, mint_0, mint_1, true, amount, &config)
+        .unwrap();
+    let (exact_out_pool, exact_out) = registry
+        .quote(None, mint_0, mint_1, false, amount, &config)
+        .unwrap();
+    assert_eq!(exact_in_pool, deep_key);
+    assert_eq!(exact_out_pool, deep_key);
+
+    // the shallow pool, asked for explicitly, quotes worse on both sides
+    let (_, shallow_in) = registry
+        .quote(Some(shallow_key), mint_0, mint_1, true, amount, &config)
+        .unwrap();
+    let (_, shallow_out) = registry
+        .quote(Some(shallow_key), mint_0, mint_1, false, amount, &config)
+        .unwrap();
+    assert!(shallow_in.out_amount < exact_in.out_amount);
+    assert!(shallow_out.in_amount > exact_out.in_amount);
+
+    let unknown_mint = Pubkey::new_unique();
+    assert!(
+        registry
+            .quote(None, mint_0, unknown_mint, true, amount, &config)
+            .is_err()
+    );
+    assert!(
+        registry
+            .quote(Some(deep_key), mint_0, unknown_mint, true, amount, &config)
+            .is_err()
+    );
+}
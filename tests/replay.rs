@@ -0,0 +1,152 @@
+//! Applying swap events to a `PoolManager` instead of refetching the pool.
+
+mod common;
+
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use swap_io_clmm::{libraries::tick_math, states::SwapEvent};
+use swap_io_clmm_rust_sdk::{
+    pool::PoolManager,
+    quote::QuoteCalculator,
+    utils::{SwapState, simulate_swap},
+};
+
+use common::{GeneratedPool, PoolAccounts, PoolKeys, mint_account};
+
+fn pool() -> GeneratedPool {
+    GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 15,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    }
+}
+
+// the state a zero_for_one swap stopping at `target_tick` leaves the pool in
+fn swap_to(pool_manager: &PoolManager, target_tick: i32) -> SwapState {
+    simulate_swap(
+        u64::MAX / 2,
+        Some(tick_math::get_sqrt_price_at_tick(target_tick).unwrap()),
+        true,
+        true,
+        pool_manager.amm_config.as_ref().unwrap(),
+        &pool_manager.pool_state,
+        pool_manager.tickarray_bitmap_extension.as_ref().unwrap(),
+        &mut pool_manager.up_tick_arrays.clone(),
+    )
+    .unwrap()
+}
+
+fn swap_event(pool_manager: &PoolManager, state: &SwapState) -> SwapEvent {
+    SwapEvent {
+        pool_state: pool_manager.pool_key,
+        sender: Pubkey::new_unique(),
+        token_account_0: Pubkey::new_unique(),
+        token_account_1: Pubkey::new_unique(),
+        amount_0: 0,
+        transfer_fee_0: 0,
+        amount_1: 0,
+        transfer_fee_1: 0,
+        zero_for_one: true,
+        sqrt_price_x64: state.sqrt_price_x64,
+        liquidity: state.liquidity,
+        tick: state.tick,
+    }
+}
+
+fn tick_array_accounts(accounts: &PoolAccounts, keys: &[Pubkey]) -> Vec<Account> {
+    keys.iter()
+        .map(|key| {
+            accounts
+                .tick_arrays
+                .iter()
+                .find(|(tick_array_key, _)| tick_array_key == key)
+                .map(|(_, account)| account.clone())
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn replays_swap_within_loaded_tick_arrays() {
+    let pool = pool();
+    let keys = PoolKeys::unique();
+    let accounts = pool.accounts(&keys);
+    let mut pool_manager = pool.load(&keys, &accounts, &mint_account(), &mint_account());
+    pool_manager
+        .update_pool_state(&accounts.pool_state, 100)
+        .unwrap();
+
+    let state = swap_to(&pool_manager, 5);
+    let event = swap_event(&pool_manager, &state);
+    // older than the loaded pool state
+    assert!(!pool_manager.apply_swap_event(&event, 99).unwrap());
+    assert!(pool_manager.apply_swap_event(&event, 101).unwrap());
+    assert_eq!(pool_manager.pool_state.tick_current, state.tick);
+    assert!(!pool_manager.tick_arrays_stale);
+    QuoteCalculator::calculate_quote(
+        keys.token_mint_0,
+        keys.token_mint_1,
+        true,
+        1_000,
+        &pool_manager,
+    )
+    .unwrap();
+
+    // the account from slot 100 predates the swap replayed at 101
+    let mismatch = pool_manager
+        .update_pool_state_checked(&accounts.pool_state, 102)
+        .unwrap()
+        .unwrap();
+    assert_eq!(mismatch.expected.tick, state.tick);
+    assert_eq!(mismatch.tick, pool.tick_current);
+    assert!(pool_manager.replayed_swap.is_none());
+}
+
+#[test]
+fn replay_across_tick_arrays_marks_them_stale() {
+    let pool = pool();
+    let keys = PoolKeys::unique();
+    let accounts = pool.accounts(&keys);
+    let mut pool_manager = pool.load(&keys, &accounts, &mint_account(), &mint_account());
+
+    let state = swap_to(&pool_manager, -1000);
+    pool_manager
+        .apply_swap_event(&swap_event(&pool_manager, &state), 1)
+        .unwrap();
+    assert!(pool_manager.tick_arrays_stale);
+    assert!(
+        QuoteCalculator::calculate_quote(
+            keys.token_mint_0,
+            keys.token_mint_1,
+            true,
+            1_000,
+            &pool_manager,
+        )
+        .is_err()
+    );
+
+    let up_ticks = tick_array_accounts(&accounts, &pool_manager.up_tick_array_keys);
+    let down_ticks = tick_array_accounts(&accounts, &pool_manager.down_tick_array_keys);
+    let mint = mint_account();
+    pool_manager
+        .update(
+            vec![
+                &accounts.amm_config,
+                &mint,
+                &mint,
+                &accounts.tickarray_bitmap_extension,
+            ],
+            up_ticks,
+            down_ticks,
+        )
+        .unwrap();
+    assert!(!pool_manager.tick_arrays_stale);
+    QuoteCalculator::calculate_quote(
+        keys.token_mint_0,
+        keys.token_mint_1,
+        true,
+        1_000,
+        &pool_manager,
+    )
+    .unwrap();
+}
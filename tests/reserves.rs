@@ -0,0 +1,143 @@
+//! Vault balances net of unclaimed protocol and fund fees.
+
+mod common;
+
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm_rust_sdk::{
+    pool::PoolManager,
+    quote::{QuoteCalculator, QuoteConfig},
+    reserves::Reserves,
+};
+
+use common::{GeneratedPool, token_account};
+
+fn pool_manager() -> PoolManager {
+    GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 0,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    }
+    .build()
+}
+
+fn load_vaults(pool_manager: &mut PoolManager, amount_0: u64, amount_1: u64) {
+    let pool_state = &pool_manager.pool_state;
+    let vault_0 = token_account(pool_state.token_mint_0, pool_manager.pool_key, amount_0);
+    let vault_1 = token_account(pool_state.token_mint_1, pool_manager.pool_key, amount_1);
+    pool_manager
+        .update_vaults_with_slot(10, &vault_0, &vault_1)
+        .unwrap();
+}
+
+#[test]
+fn reserves_exclude_unclaimed_fees() {
+    let mut pool_manager = pool_manager();
+    assert!(pool_manager.reserves().is_err());
+
+    pool_manager.pool_state.protocol_fees_token_0 = 300;
+    pool_manager.pool_state.fund_fees_token_0 = 200;
+    pool_manager.pool_state.protocol_fees_token_1 = 1_000;
+    load_vaults(&mut pool_manager, 2_000_500, 3_001_000);
+    let reserves = pool_manager.reserves().unwrap();
+    assert_eq!(
+        reserves,
+        Reserves {
+            vault_amount_0: 2_000_500,
+            vault_amount_1: 3_001_000,
+            protocol_fees_0: 300,
+            protocol_fees_1: 1_000,
+            fund_fees_0: 200,
+            fund_fees_1: 0,
+        }
+    );
+    assert_eq!(reserves.reserve_0(), 2_000_000);
+    assert_eq!(reserves.reserve_1(), 3_000_000);
+
+    // price 1 at tick 0, both mints with 6 decimals
+    assert_eq!(pool_manager.tvl().unwrap(), Decimal::from(5));
+    assert_eq!(
+        reserves.tvl(Decimal::from(2), 6, 6).unwrap(),
+        Decimal::from(7)
+    );
+
+    // fees exceeding the vault leave nothing withdrawable
+    pool_manager.pool_state.fund_fees_token_1 = u64::MAX;
+    assert_eq!(pool_manager.reserves().unwrap().reserve_1(), 0);
+}
+
+#[test]
+fn vaults_must_belong_to_the_pool() {
+    let mut pool_manager = pool_manager();
+    let pool_state = pool_manager.pool_state;
+    let pool_key = pool_manager.pool_key;
+    let vault_0 = token_account(pool_state.token_mint_0, pool_key, 1);
+    let vault_1 = token_account(pool_state.token_mint_1, pool_key, 1);
+
+    let swapped = pool_manager.update_vaults(&vault_1, &vault_0).unwrap_err();
+    assert!(swapped.to_string().contains("Vault holds mint"));
+    let foreign = token_account(pool_state.token_mint_1, Pubkey::new_unique(), 1);
+    assert!(pool_manager.update_vaults(&vault_0, &foreign).is_err());
+    assert!(pool_manager.vault_amounts.is_none());
+
+    pool_manager.update_vaults(&vault_0, &vault_1).unwrap();
+    assert_eq!(pool_manager.vault_amounts, Some((1, 1)));
+}
+
+#[test]
+fn vaults_are_tracked_with_their_slot() {
+    let mut pool_manager = pool_manager();
+    let pool_state = pool_manager.pool_state;
+    assert!(
+        !pool_manager
+            .get_loaded_account_keys()
+            .contains(&pool_state.token_vault_0)
+    );
+
+    load_vaults(&mut pool_manager, 1, 1);
+    let keys = pool_manager.get_loaded_account_keys();
+    assert!(keys.contains(&pool_state.token_vault_0));
+    assert!(keys.contains(&pool_state.token_vault_1));
+    assert_eq!(
+        pool_manager.get_account_slot(&pool_state.token_vault_1),
+        Some(10)
+    );
+}
+
+#[test]
+fn quotes_never_exceed_the_output_reserve() {
+    let mut pool_manager = pool_manager();
+    let (mint_0, mint_1) = (
+        pool_manager.pool_state.token_mint_0,
+        pool_manager.pool_state.token_mint_1,
+    );
+    let quote = |pool_manager: &PoolManager, base_in: bool, amount: u64| {
+        QuoteCalculator::calculate_quote(mint_0, mint_1, base_in, amount, pool_manager)
+    };
+    // without vaults nothing to check against
+    assert!(quote(&pool_manager, true, 2_000_000).is_ok());
+
+    load_vaults(&mut pool_manager, 10_000_000, 1_000_500);
+    pool_manager.pool_state.protocol_fees_token_1 = 500;
+    assert!(quote(&pool_manager, true, 500_000).is_ok());
+    assert!(quote(&pool_manager, false, 1_000_000).is_ok());
+    let err = quote(&pool_manager, true, 2_000_000).unwrap_err();
+    assert!(err.to_string().contains("holds 1000000"));
+    assert!(quote(&pool_manager, false, 1_000_001).is_err());
+
+    // the other direction draws on the mint 0 reserve
+    assert!(
+        QuoteCalculator::calculate_quote(mint_1, mint_0, true, 2_000_000, &pool_manager).is_ok()
+    );
+
+    // moving the price down 0.1% takes far more mint 1 out than the vault holds
+    let sqrt_price_x64 = pool_manager.pool_state.sqrt_price_x64;
+    let err = QuoteCalculator::calculate_amount_to_sqrt_price(
+        sqrt_price_x64 - sqrt_price_x64 / 2_000,
+        &pool_manager,
+        &QuoteConfig::default(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("holds 1000000"));
+}
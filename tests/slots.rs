@@ -0,0 +1,128 @@
+//! Quotes refused on accounts loaded too far apart or too long ago.
+
+mod common;
+
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm_rust_sdk::{
+    pool::PoolManager,
+    quote::{QuoteCalculator, QuoteConfig},
+};
+
+use common::{GeneratedPool, PoolKeys, mint_account};
+
+// every cached account at slot 100, but the AmmConfig loaded at 90
+fn pool_manager() -> PoolManager {
+    let mut pool_manager = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 0,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    }
+    .build();
+    for key in pool_manager.get_loaded_account_keys() {
+        pool_manager.account_slots.insert(key, 100);
+    }
+    let amm_config = pool_manager.pool_state.amm_config;
+    pool_manager.account_slots.insert(amm_config, 90);
+    pool_manager
+}
+
+fn quote(pool_manager: &PoolManager, config: QuoteConfig) -> anyhow::Result<()> {
+    let pool_state = &pool_manager.pool_state;
+    QuoteCalculator::calculate_quote_with_config(
+        pool_state.token_mint_0,
+        pool_state.token_mint_1,
+        true,
+        1_000_000,
+        pool_manager,
+        &config,
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn slot_spread_too_wide_is_rejected() {
+    let pool_manager = pool_manager();
+    assert_eq!(pool_manager.get_slot_range(), Some((90, 100)));
+    assert!(pool_manager.check_slot_consistency(10).is_ok());
+    let err = pool_manager.check_slot_consistency(9).unwrap_err();
+    assert!(err.to_string().contains("exceeds spread 9"));
+
+    let spread = |max_slot_spread| QuoteConfig {
+        max_slot_spread: Some(max_slot_spread),
+        ..QuoteConfig::default()
+    };
+    assert!(quote(&pool_manager, spread(10)).is_ok());
+    assert!(quote(&pool_manager, spread(5)).is_err());
+}
+
+#[test]
+fn stale_slots_are_rejected() {
+    let pool_manager = pool_manager();
+    // the oldest account decides the age
+    assert!(pool_manager.check_slot_staleness(140, 50).is_ok());
+    let err = pool_manager.check_slot_staleness(141, 50).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("oldest slot 90 is 51 slots behind 141")
+    );
+
+    let age = |current_slot| QuoteConfig {
+        current_slot,
+        max_slot_age: Some(50),
+        ..QuoteConfig::default()
+    };
+    assert!(quote(&pool_manager, age(Some(120))).is_ok());
+    assert!(quote(&pool_manager, age(Some(200))).is_err());
+    // an age limit needs the current slot
+    assert!(quote(&pool_manager, age(None)).is_err());
+}
+
+#[test]
+fn accounts_without_a_slot_fail_the_checks() {
+    let mut pool_manager = pool_manager();
+    let key = pool_manager.pool_key;
+    pool_manager.account_slots.remove(&key);
+    assert_eq!(pool_manager.get_slot_range(), None);
+    assert!(pool_manager.check_slot_consistency(u64::MAX).is_err());
+    assert!(pool_manager.check_slot_staleness(100, u64::MAX).is_err());
+    // without limits nothing is checked
+    assert!(quote(&pool_manager, QuoteConfig::default()).is_ok());
+}
+
+#[test]
+fn slots_of_a_pool_under_another_program_id_line_up() {
+    let pool = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 0,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    };
+    let keys = PoolKeys::unique_with_program_id(Pubkey::new_unique());
+    let accounts = pool.accounts(&keys);
+    let mut pool_manager = pool.load(&keys, &accounts, &mint_account(), &mint_account());
+    assert_eq!(
+        pool_manager.get_up_tick_array_keys(),
+        pool_manager.up_tick_array_keys
+    );
+    assert_eq!(
+        pool_manager.get_down_tick_array_keys(),
+        pool_manager.down_tick_array_keys
+    );
+
+    // slots recorded under the addresses the accounts were fetched from
+    let mut fetched = vec![
+        keys.pool_key,
+        keys.amm_config,
+        keys.token_mint_0,
+        keys.token_mint_1,
+        keys.tickarray_bitmap_extension(),
+    ];
+    fetched.extend(pool_manager.up_tick_array_keys.clone());
+    fetched.extend(pool_manager.down_tick_array_keys.clone());
+    for key in fetched {
+        pool_manager.account_slots.insert(key, 100);
+    }
+    assert_eq!(pool_manager.get_slot_range(), Some((100, 100)));
+    assert!(pool_manager.check_slot_staleness(120, 50).is_ok());
+}
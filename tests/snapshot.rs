@@ -0,0 +1,220 @@
+//! Snapshots reload into a manager quoting exactly like the original.
+
+mod common;
+
+use std::collections::VecDeque;
+
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm::states::TickArrayState;
+use swap_io_clmm_rust_sdk::{
+    pool::PoolManager,
+    quote::QuoteCalculator,
+    snapshot::PoolSnapshot,
+    utils::{serialize_anchor_account, serialize_zero_copy_account},
+};
+
+use common::{
+    GeneratedPool, PoolKeys, Position, TRADE_FEE_RATE, mint_account, program_account, token_account,
+};
+
+fn pool() -> GeneratedPool {
+    GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 15,
+        wide_liquidity: 1_000_000_000_000,
+        positions: vec![Position {
+            tick_lower: -600,
+            tick_upper: 300,
+            liquidity: 3_000_000_000_000,
+        }],
+    }
+}
+
+fn pool_manager() -> PoolManager {
+    pool().build()
+}
+
+// a binary snapshot as written before the format version: no tag, no vault amounts
+fn unversioned_snapshot_bytes(pool_manager: &PoolManager) -> Vec<u8> {
+    let tick_arrays = |tick_arrays: &VecDeque<TickArrayState>| -> Vec<Vec<u8>> {
+        tick_arrays
+            .iter()
+            .map(serialize_zero_copy_account)
+            .collect()
+    };
+    let mut account_slots: Vec<(Pubkey, u64)> = pool_manager
+        .account_slots
+        .iter()
+        .map(|(key, slot)| (*key, *slot))
+        .collect();
+    account_slots.sort_by_key(|(key, _)| *key);
+    bincode::serialize(&(
+        pool_manager.epoch,
+        pool_manager.pool_key,
+        pool_manager.program_id,
+        serialize_zero_copy_account(&pool_manager.pool_state),
+        pool_manager
+            .amm_config
+            .as_ref()
+            .map(|amm_config| serialize_anchor_account(amm_config).unwrap()),
+        pool_manager
+            .tickarray_bitmap_extension
+            .as_ref()
+            .map(serialize_zero_copy_account),
+        tick_arrays(&pool_manager.up_tick_arrays),
+        tick_arrays(&pool_manager.down_tick_arrays),
+        pool_manager.mint0_data.clone(),
+        pool_manager.mint1_data.clone(),
+        pool_manager.up_tick_array_keys.clone(),
+        pool_manager.down_tick_array_keys.clone(),
+        account_slots,
+    ))
+    .unwrap()
+}
+
+fn assert_same_quotes(original: &PoolManager, reloaded: &PoolManager) {
+    let pool_state = &original.pool_state;
+    for (input_mint, output_mint) in [
+        (pool_state.token_mint_0, pool_state.token_mint_1),
+        (pool_state.token_mint_1, pool_state.token_mint_0),
+    ] {
+        for base_in in [true, false] {
+            let quote = |pool_manager: &PoolManager| {
+                QuoteCalculator::calculate_quote(
+                    input_mint,
+                    output_mint,
+                    base_in,
+                    50_000_000_000,
+                    pool_manager,
+                )
+                .unwrap()
+            };
+            let (expected, actual) = (quote(original), quote(reloaded));
+            assert_eq!(actual.in_amount, expected.in_amount);
+            assert_eq!(actual.out_amount, expected.out_amount);
+            assert_eq!(actual.fee_amount, expected.fee_amount);
+        }
+    }
+}
+
+#[test]
+fn json_round_trip_quotes_identically() {
+    let pool_manager = pool_manager();
+    let json = pool_manager.to_snapshot().unwrap().to_json().unwrap();
+    let reloaded = PoolSnapshot::from_json(&json)
+        .unwrap()
+        .to_pool_manager()
+        .unwrap();
+    assert_same_quotes(&pool_manager, &reloaded);
+    // writing the reloaded manager again gives the same file
+    assert_eq!(reloaded.to_snapshot().unwrap().to_json().unwrap(), json);
+}
+
+#[test]
+fn binary_round_trip_quotes_identically() {
+    let pool_manager = pool_manager();
+    let bytes = pool_manager.to_snapshot().unwrap().to_bytes().unwrap();
+    let reloaded = PoolSnapshot::from_bytes(&bytes)
+        .unwrap()
+        .to_pool_manager()
+        .unwrap();
+    assert_same_quotes(&pool_manager, &reloaded);
+}
+
+#[test]
+fn json_snapshot_shows_decoded_fields() {
+    let pool_manager = pool_manager();
+    let json = pool_manager.to_snapshot().unwrap().to_json().unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let summary = &value["summary"];
+    assert_eq!(summary["pool_state"]["tick_current"], 15);
+    assert_eq!(summary["pool_state"]["tick_spacing"], 10);
+    assert_eq!(
+        summary["pool_state"]["token_mint_0"],
+        pool_manager.pool_state.token_mint_0.to_string()
+    );
+    assert_eq!(summary["fee_tier"]["trade_fee_rate"], TRADE_FEE_RATE);
+    assert_eq!(summary["mint_0"]["decimals"], 6);
+
+    // the position's lower tick is among the initialized ticks listed
+    let snapshot = PoolSnapshot::from_json(&json).unwrap();
+    let summary = snapshot.summary();
+    let lower = summary
+        .up_tick_arrays
+        .iter()
+        .chain(summary.down_tick_arrays.iter())
+        .flat_map(|tick_array| tick_array.ticks.iter())
+        .find(|tick| tick.tick == -600)
+        .unwrap();
+    assert_eq!(lower.liquidity_net, 3_000_000_000_000);
+}
+
+#[test]
+fn binary_snapshots_are_versioned_and_leave_out_the_summary() {
+    let pool_manager = pool_manager();
+    let snapshot = pool_manager.to_snapshot().unwrap();
+    let bytes = snapshot.to_bytes().unwrap();
+    assert!(bytes.starts_with(b"CLMMSNAP"));
+    // the summary is rebuilt from the accounts
+    let reloaded = PoolSnapshot::from_bytes(&bytes).unwrap();
+    assert_eq!(reloaded.summary(), snapshot.summary());
+
+    let mut future = bytes.clone();
+    future[8] += 1;
+    let err = PoolSnapshot::from_bytes(&future).unwrap_err();
+    assert!(err.to_string().contains("not supported"));
+}
+
+#[test]
+fn unversioned_binary_snapshot_still_loads() {
+    let pool_manager = pool_manager();
+    let snapshot = PoolSnapshot::from_bytes(&unversioned_snapshot_bytes(&pool_manager)).unwrap();
+    assert_eq!(snapshot.summary().pool_state.tick_current, 15);
+    let reloaded = snapshot.to_pool_manager().unwrap();
+    assert_same_quotes(&pool_manager, &reloaded);
+    assert_eq!(reloaded.account_slots, pool_manager.account_slots);
+}
+
+#[test]
+fn unreadable_mint_is_left_out_of_the_summary() {
+    let pool = pool();
+    let keys = PoolKeys::unique();
+    let garbage = program_account(vec![7; 20], spl_token::id());
+    let pool_manager = pool.load(&keys, &pool.accounts(&keys), &garbage, &mint_account());
+    let snapshot = pool_manager.to_snapshot().unwrap();
+    assert_eq!(snapshot.summary().mint_0, None);
+    assert_eq!(snapshot.summary().mint_1.as_ref().unwrap().decimals, 6);
+    let json = snapshot.to_json().unwrap();
+    assert_eq!(
+        PoolSnapshot::from_json(&json).unwrap().summary(),
+        snapshot.summary()
+    );
+}
+
+#[test]
+fn vault_amounts_survive_binary_snapshots() {
+    let mut pool_manager = pool_manager();
+    let pool_state = &pool_manager.pool_state;
+    let vault_0 = token_account(pool_state.token_mint_0, pool_manager.pool_key, 1_000);
+    let vault_1 = token_account(pool_state.token_mint_1, pool_manager.pool_key, 2_000);
+    pool_manager
+        .update_vaults_with_slot(10, &vault_0, &vault_1)
+        .unwrap();
+
+    let bytes = pool_manager.to_snapshot().unwrap().to_bytes().unwrap();
+    let reloaded = PoolSnapshot::from_bytes(&bytes)
+        .unwrap()
+        .to_pool_manager()
+        .unwrap();
+    assert_eq!(reloaded.vault_amounts, Some((1_000, 2_000)));
+    assert_eq!(reloaded.mint0_owner, Some(spl_token::id()));
+
+    // an unversioned snapshot predates vault tracking
+    let unversioned = PoolSnapshot::from_bytes(&unversioned_snapshot_bytes(&pool_manager))
+        .unwrap()
+        .to_pool_manager()
+        .unwrap();
+    assert_eq!(unversioned.vault_amounts, None);
+    assert_eq!(unversioned.mint0_owner, None);
+    assert!(unversioned.reserves().is_err());
+}
@@ -0,0 +1,316 @@
+//! Property tests for the swap engine over generated pools.
+//!
+//! Pools are built in memory from random positions, loaded through
+//! `PoolManager::new`/`update` so the tick arrays the swap walks are the ones
+//! the manager navigated to, then quoted through `QuoteCalculator`. The same pools
+//! under another program id, with Token-2022 mints or restored from a binary snapshot
+//! must quote alike.
+
+mod common;
+
+use proptest::prelude::*;
+use solana_sdk::pubkey::Pubkey;
+use swap_io_clmm::{libraries::tick_math, states::TickArrayState};
+use swap_io_clmm_rust_sdk::{
+    pool::PoolManager,
+    quote::{Quote, QuoteCalculator, QuoteConfig},
+    snapshot::PoolSnapshot,
+    utils::{simulate_swap, tick_with_spacing},
+};
+
+use common::{
+    FUND_FEE_RATE, GeneratedPool, PROTOCOL_FEE_RATE, PoolKeys, Position, token_2022_mint_account,
+};
+
+fn pool_strategy(tick_current: impl Strategy<Value = i32>) -> impl Strategy<Value = GeneratedPool> {
+    (
+        prop::sample::select(vec![1u16, 10, 60]),
+        tick_current,
+        1_000_000_000u128..1_000_000_000_000_000,
+        prop::collection::vec(
+            (-3.0f64..3.0, -3.0f64..3.0, 1_000_000u128..1_000_000_000_000),
+            0..4,
+        ),
+    )
+        .prop_map(
+            |(tick_spacing, tick_current, wide_liquidity, raw_positions)| {
+                let span = TickArrayState::tick_count(tick_spacing) as f64;
+                let spacing = tick_spacing as i32;
+                let positions = raw_positions
+                    .into_iter()
+                    .filter_map(|(a, b, liquidity)| {
+                        let a = tick_with_spacing(tick_current + (a * span) as i32, spacing);
+                        let b = tick_with_spacing(tick_current + (b * span) as i32, spacing);
+                        (a != b).then(|| Position {
+                            tick_lower: a.min(b),
+                            tick_upper: a.max(b),
+                            liquidity,
+                        })
+                    })
+                    .collect();
+                GeneratedPool {
+                    tick_spacing,
+                    tick_current,
+                    wide_liquidity,
+                    positions,
+                }
+            },
+        )
+}
+
+fn mints(pool_manager: &PoolManager, zero_for_one: bool) -> (Pubkey, Pubkey) {
+    let (mint_0, mint_1) = (
+        pool_manager.pool_state.token_mint_0,
+        pool_manager.pool_state.token_mint_1,
+    );
+    if zero_for_one {
+        (mint_0, mint_1)
+    } else {
+        (mint_1, mint_0)
+    }
+}
+
+fn quote(pool_manager: &PoolManager, zero_for_one: bool, base_in: bool, amount: u64) -> Quote {
+    let (input_mint, output_mint) = mints(pool_manager, zero_for_one);
+    QuoteCalculator::calculate_quote(input_mint, output_mint, base_in, amount, pool_manager)
+        .unwrap()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn output_is_monotonic_in_input(
+        pool in pool_strategy(-1000i32..1000),
+        zero_for_one in any::<bool>(),
+        fractions in (0.0f64..1.0, 0.0f64..1.0),
+    ) {
+        let pool_manager = pool.build();
+        let (input_mint, output_mint) = mints(&pool_manager, zero_for_one);
+        let small = ((fractions.0.min(fractions.1) * pool.max_amount() as f64) as u64).max(1);
+        let large = ((fractions.0.max(fractions.1) * pool.max_amount() as f64) as u64).max(small);
+
+        let small_quote =
+            QuoteCalculator::calculate_quote(input_mint, output_mint, true, small, &pool_manager)
+                .unwrap();
+        let large_quote =
+            QuoteCalculator::calculate_quote(input_mint, output_mint, true, large, &pool_manager)
+                .unwrap();
+        prop_assert!(small_quote.out_amount <= large_quote.out_amount);
+    }
+
+    #[test]
+    fn fee_never_exceeds_input(
+        pool in pool_strategy(-1000i32..1000),
+        zero_for_one in any::<bool>(),
+        base_in in any::<bool>(),
+        fraction in 0.0f64..1.0,
+    ) {
+        let pool_manager = pool.build();
+        let (input_mint, output_mint) = mints(&pool_manager, zero_for_one);
+        // exact-out amounts are in the output token, keep them inside the pool as well
+        let amount = ((fraction * pool.max_amount() as f64 / 2.0) as u64).max(1);
+
+        let quote =
+            QuoteCalculator::calculate_quote(input_mint, output_mint, base_in, amount, &pool_manager)
+                .unwrap();
+        prop_assert!(quote.fee_amount <= quote.in_amount);
+    }
+
+    #[test]
+    fn fee_split_adds_up(
+        pool in pool_strategy(-1000i32..1000),
+        zero_for_one in any::<bool>(),
+        base_in in any::<bool>(),
+        fraction in 0.0f64..1.0,
+    ) {
+        let pool_manager = pool.build();
+        let (input_mint, output_mint) = mints(&pool_manager, zero_for_one);
+        let amount = ((fraction * pool.max_amount() as f64 / 2.0) as u64).max(1);
+
+        let quote =
+            QuoteCalculator::calculate_quote(input_mint, output_mint, base_in, amount, &pool_manager)
+                .unwrap();
+        prop_assert_eq!(
+            quote.lp_fee_amount + quote.protocol_fee_amount + quote.fund_fee_amount,
+            quote.fee_amount
+        );
+        // the fund share comes out of the fee left after the protocol share, so rounding
+        // the protocol share down can raise it by under one unit over the swap. Each step
+        // rounds both shares down, the swap takes at most a dozen steps
+        let fee_amount = quote.fee_amount as u128;
+        let protocol_exact = fee_amount * PROTOCOL_FEE_RATE as u128 / 1_000_000;
+        let fund_exact = fee_amount
+            * (1_000_000 - PROTOCOL_FEE_RATE) as u128
+            * FUND_FEE_RATE as u128
+            / 1_000_000_000_000;
+        prop_assert!(quote.protocol_fee_amount as u128 <= protocol_exact);
+        prop_assert!(quote.protocol_fee_amount as u128 + 12 >= protocol_exact);
+        prop_assert!(quote.fund_fee_amount as u128 <= fund_exact + 1);
+        prop_assert!(quote.fund_fee_amount as u128 + 12 >= fund_exact);
+    }
+
+    #[test]
+    fn exact_in_then_exact_out_round_trips(
+        pool in pool_strategy(prop_oneof![-1000i32..-100, 100i32..1000]),
+        fraction in 0.0f64..1.0,
+    ) {
+        // one unit of output never costs more than one unit of input at these prices,
+        // so rounding differences stay within a single unit
+        let zero_for_one = pool.tick_current > 0;
+        let pool_manager = pool.build();
+        let (input_mint, output_mint) = mints(&pool_manager, zero_for_one);
+        let amount = ((fraction * pool.max_amount() as f64) as u64).max(1);
+
+        let exact_in =
+            QuoteCalculator::calculate_quote(input_mint, output_mint, true, amount, &pool_manager)
+                .unwrap();
+        prop_assume!(exact_in.out_amount > 0);
+        let exact_out = QuoteCalculator::calculate_quote(
+            input_mint,
+            output_mint,
+            false,
+            exact_in.out_amount,
+            &pool_manager,
+        )
+        .unwrap();
+        prop_assert!(exact_out.in_amount <= amount + 1);
+        prop_assert!(amount - exact_out.in_amount.min(amount) <= 1);
+    }
+
+    #[test]
+    fn price_moves_in_swap_direction(
+        pool in pool_strategy(-1000i32..1000),
+        zero_for_one in any::<bool>(),
+        base_in in any::<bool>(),
+        fraction in 0.0f64..1.0,
+    ) {
+        let pool_manager = pool.build();
+        let amount = ((fraction * pool.max_amount() as f64 / 2.0) as u64).max(1);
+        let mut tick_arrays = if zero_for_one {
+            pool_manager.up_tick_arrays.clone()
+        } else {
+            pool_manager.down_tick_arrays.clone()
+        };
+
+        let state = simulate_swap(
+            amount,
+            None,
+            zero_for_one,
+            base_in,
+            pool_manager.amm_config.as_ref().unwrap(),
+            &pool_manager.pool_state,
+            pool_manager.tickarray_bitmap_extension.as_ref().unwrap(),
+            &mut tick_arrays,
+        )
+        .unwrap();
+        let start_sqrt_price_x64 = pool_manager.pool_state.sqrt_price_x64;
+        if zero_for_one {
+            prop_assert!(state.sqrt_price_x64 <= start_sqrt_price_x64);
+            prop_assert!(state.tick <= pool.tick_current);
+        } else {
+            prop_assert!(state.sqrt_price_x64 >= start_sqrt_price_x64);
+            prop_assert!(state.tick >= pool.tick_current);
+        }
+        if state.amount_calculated > 0 && base_in {
+            prop_assert!(state.sqrt_price_x64 != start_sqrt_price_x64);
+        }
+    }
+
+    #[test]
+    fn quotes_do_not_depend_on_the_deployment_or_token_program(
+        pool in pool_strategy(-1000i32..1000),
+        zero_for_one in any::<bool>(),
+        base_in in any::<bool>(),
+        fraction in 0.0f64..1.0,
+    ) {
+        // every address moves with the program id, tick arrays included. Token-2022
+        // mints without extensions charge no transfer fee
+        let keys = PoolKeys::unique_with_program_id(Pubkey::new_unique());
+        let mint = token_2022_mint_account(&[], |_| {});
+        let elsewhere = pool.load(&keys, &pool.accounts(&keys), &mint, &mint);
+        let pool_manager = pool.build();
+        let amount = ((fraction * pool.max_amount() as f64 / 2.0) as u64).max(1);
+
+        let expected = quote(&pool_manager, zero_for_one, base_in, amount);
+        let actual = quote(&elsewhere, zero_for_one, base_in, amount);
+        prop_assert_eq!(
+            (actual.in_amount, actual.out_amount, actual.fee_amount),
+            (expected.in_amount, expected.out_amount, expected.fee_amount)
+        );
+    }
+
+    #[test]
+    fn binary_snapshots_quote_like_the_pool(
+        pool in pool_strategy(-1000i32..1000),
+        zero_for_one in any::<bool>(),
+        base_in in any::<bool>(),
+        fraction in 0.0f64..1.0,
+    ) {
+        let pool_manager = pool.build();
+        let bytes = PoolSnapshot::from_pool_manager(&pool_manager)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        let restored = PoolSnapshot::from_bytes(&bytes)
+            .unwrap()
+            .to_pool_manager()
+            .unwrap();
+        let amount = ((fraction * pool.max_amount() as f64 / 2.0) as u64).max(1);
+
+        let expected = quote(&pool_manager, zero_for_one, base_in, amount);
+        let actual = quote(&restored, zero_for_one, base_in, amount);
+        prop_assert_eq!(
+            (actual.in_amount, actual.out_amount, actual.fee_amount),
+            (expected.in_amount, expected.out_amount, expected.fee_amount)
+        );
+    }
+
+    #[test]
+    fn amount_to_price_is_the_exact_input_reaching_it(
+        pool in pool_strategy(-1000i32..1000),
+        offset in prop_oneof![-1.5f64..-0.01, 0.01f64..1.5],
+    ) {
+        // stay inside the wide position, which spans two tick arrays on each side
+        let span = TickArrayState::tick_count(pool.tick_spacing) as f64;
+        let target_tick = pool.tick_current + (offset * span) as i32;
+        let target = tick_math::get_sqrt_price_at_tick(target_tick).unwrap();
+        let pool_manager = pool.build();
+
+        let solved =
+            QuoteCalculator::calculate_amount_to_sqrt_price(target, &pool_manager, &QuoteConfig::default())
+                .unwrap();
+        prop_assert_eq!(solved.sqrt_price_x64, target);
+        prop_assume!(solved.in_amount > 0);
+        let quote = QuoteCalculator::calculate_quote(
+            solved.input_mint,
+            solved.output_mint,
+            true,
+            solved.in_amount,
+            &pool_manager,
+        )
+        .unwrap();
+        prop_assert!(quote.out_amount.abs_diff(solved.out_amount) <= 1);
+        prop_assert!(quote.fee_amount.abs_diff(solved.fee_amount) <= 1);
+    }
+}
+
+#[test]
+fn amount_to_price_reports_unreachable_targets() {
+    let pool_manager = GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 15,
+        wide_liquidity: 10_000_000_000_000,
+        positions: vec![],
+    }
+    .build();
+    let target = tick_math::get_sqrt_price_at_tick(tick_math::MIN_TICK + 1).unwrap();
+    assert!(
+        QuoteCalculator::calculate_amount_to_sqrt_price(
+            target,
+            &pool_manager,
+            &QuoteConfig::default()
+        )
+        .is_err()
+    );
+}
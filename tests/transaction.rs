@@ -0,0 +1,300 @@
+//! Instruction layout and compute estimates of `SwapTransactionBuilder`.
+
+mod common;
+
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount, compute_budget, hash::Hash,
+    instruction::Instruction, pubkey::Pubkey, system_program,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use swap_io_clmm_rust_sdk::{
+    lookup_table::pool_lookup_table_addresses,
+    quote::QuoteCalculator,
+    transaction::{SwapTransactionBuilder, SwapTransactionOptions},
+};
+
+use common::{GeneratedPool, PoolKeys, Position, mint_account, token_2022_mint_account};
+
+fn pool() -> GeneratedPool {
+    GeneratedPool {
+        tick_spacing: 10,
+        tick_current: 15,
+        wide_liquidity: 10_000_000_000_000,
+        // initialized ticks in the three arrays below the current price
+        positions: vec![
+            Position {
+                tick_lower: -50,
+                tick_upper: 50,
+                liquidity: 1_000_000_000_000,
+            },
+            Position {
+                tick_lower: -1100,
+                tick_upper: -500,
+                liquidity: 2_000_000_000_000,
+            },
+        ],
+    }
+}
+
+fn program_ids(instructions: &[Instruction]) -> Vec<Pubkey> {
+    instructions
+        .iter()
+        .map(|instruction| instruction.program_id)
+        .collect()
+}
+
+#[test]
+fn wraps_and_unwraps_native_sol() {
+    let pool = pool();
+    let native_mint = spl_token::native_mint::id();
+    let keys = PoolKeys::new(
+        native_mint,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let accounts = pool.accounts(&keys);
+    let pool_manager = pool.load(&keys, &accounts, &mint_account(), &mint_account());
+    let wallet = Pubkey::new_unique();
+    let quote = QuoteCalculator::calculate_quote(
+        native_mint,
+        keys.token_mint_1,
+        true,
+        1_000_000,
+        &pool_manager,
+    )
+    .unwrap();
+    let builder = SwapTransactionBuilder::new(
+        &pool_manager,
+        wallet,
+        native_mint,
+        keys.token_mint_1,
+        true,
+        quote,
+    )
+    .options(SwapTransactionOptions {
+        compute_unit_price_micro_lamports: Some(1_000),
+        memo: Some("swap".to_string()),
+        ..SwapTransactionOptions::default()
+    });
+
+    let instructions = builder.build_instructions().unwrap();
+    assert_eq!(
+        program_ids(&instructions),
+        vec![
+            compute_budget::id(),
+            compute_budget::id(),
+            spl_associated_token_account::id(),
+            system_program::id(),
+            spl_token::id(),
+            spl_associated_token_account::id(),
+            keys.program_id,
+            spl_token::id(),
+            spl_memo::id(),
+        ]
+    );
+    let transaction = builder.build_transaction(Hash::new_unique()).unwrap();
+    assert_eq!(transaction.signatures.len(), 1);
+    assert_eq!(transaction.message.static_account_keys()[0], wallet);
+}
+
+#[test]
+fn compute_estimate_grows_with_tick_arrays_crossed() {
+    let pool = pool();
+    let pool_manager = pool.build();
+    let (input_mint, output_mint) = (
+        pool_manager.pool_state.token_mint_0,
+        pool_manager.pool_state.token_mint_1,
+    );
+    let builder = |amount| {
+        let quote =
+            QuoteCalculator::calculate_quote(input_mint, output_mint, true, amount, &pool_manager)
+                .unwrap();
+        SwapTransactionBuilder::new(
+            &pool_manager,
+            Pubkey::new_unique(),
+            input_mint,
+            output_mint,
+            true,
+            quote,
+        )
+    };
+
+    // enough to push the price from tick 15 past tick -600
+    let small = builder(1_000);
+    let large = builder(500_000_000_000);
+    assert!(large.tick_arrays_crossed().unwrap() > small.tick_arrays_crossed().unwrap());
+    assert!(large.estimate_compute_units().unwrap() > small.estimate_compute_units().unwrap());
+}
+
+#[test]
+fn pool_lookup_table_shrinks_the_message() {
+    let pool_manager = pool().build();
+    let (input_mint, output_mint) = (
+        pool_manager.pool_state.token_mint_0,
+        pool_manager.pool_state.token_mint_1,
+    );
+    let quote =
+        QuoteCalculator::calculate_quote(input_mint, output_mint, true, 1_000, &pool_manager)
+            .unwrap();
+    let builder = SwapTransactionBuilder::new(
+        &pool_manager,
+        Pubkey::new_unique(),
+        input_mint,
+        output_mint,
+        true,
+        quote,
+    );
+    let lookup_table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: pool_lookup_table_addresses(&pool_manager),
+    };
+    let blockhash = Hash::new_unique();
+
+    let without_table = builder.build_transaction(blockhash).unwrap();
+    let with_table = builder
+        .build_transaction_with_lookup_tables(blockhash, &[lookup_table.clone()])
+        .unwrap();
+    let static_keys = with_table.message.static_account_keys();
+    assert!(static_keys.len() < without_table.message.static_account_keys().len());
+    // the invoked clmm program can not be loaded from a table
+    assert!(static_keys.contains(&pool_manager.program_id));
+    assert!(!static_keys.contains(&pool_manager.pool_state.token_vault_0));
+    assert_eq!(
+        with_table.message.address_table_lookups().unwrap()[0].account_key,
+        lookup_table.key
+    );
+    assert!(
+        bincode::serialize(&with_table).unwrap().len()
+            < bincode::serialize(&without_table).unwrap().len()
+    );
+}
+
+#[test]
+fn only_a_wsol_account_created_here_is_closed() {
+    let pool = pool();
+    let native_mint = spl_token::native_mint::id();
+    let keys = PoolKeys::new(
+        native_mint,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let accounts = pool.accounts(&keys);
+    let pool_manager = pool.load(&keys, &accounts, &mint_account(), &mint_account());
+    let instructions = |input_mint: Pubkey, output_mint: Pubkey, wsol_account_exists: bool| {
+        let quote = QuoteCalculator::calculate_quote(
+            input_mint,
+            output_mint,
+            true,
+            1_000_000,
+            &pool_manager,
+        )
+        .unwrap();
+        SwapTransactionBuilder::new(
+            &pool_manager,
+            Pubkey::new_unique(),
+            input_mint,
+            output_mint,
+            true,
+            quote,
+        )
+        .options(SwapTransactionOptions {
+            wsol_account_exists,
+            ..SwapTransactionOptions::default()
+        })
+        .build_instructions()
+        .unwrap()
+    };
+
+    // SOL received into a new wSOL ata, created without the idempotent instruction
+    let unwrapped = instructions(keys.token_mint_1, native_mint, false);
+    assert_eq!(
+        program_ids(&unwrapped),
+        vec![
+            compute_budget::id(),
+            spl_associated_token_account::id(),
+            keys.program_id,
+            spl_token::id(),
+        ]
+    );
+    assert_eq!(unwrapped[1].data, vec![0]);
+
+    // an existing wSOL ata is wrapped into but left open
+    let wrapped = instructions(native_mint, keys.token_mint_1, true);
+    assert_eq!(
+        program_ids(&wrapped),
+        vec![
+            compute_budget::id(),
+            system_program::id(),
+            spl_token::id(),
+            spl_associated_token_account::id(),
+            keys.program_id,
+        ]
+    );
+    let kept = instructions(keys.token_mint_1, native_mint, true);
+    assert_eq!(program_ids(&kept).last(), Some(&keys.program_id));
+}
+
+#[test]
+fn token_2022_mint_without_extensions_gets_a_token_2022_account() {
+    let pool = pool();
+    let keys = PoolKeys::unique();
+    let accounts = pool.accounts(&keys);
+    // no extensions, so exactly as long as an spl-token mint
+    let mint1 = token_2022_mint_account(&[], |_| {});
+    assert_eq!(mint1.data.len(), mint_account().data.len());
+    let mut pool_manager = pool.load(&keys, &accounts, &mint_account(), &mint1);
+    let wallet = Pubkey::new_unique();
+    let quote = QuoteCalculator::calculate_quote(
+        keys.token_mint_0,
+        keys.token_mint_1,
+        true,
+        1_000_000,
+        &pool_manager,
+    )
+    .unwrap();
+    let builder = SwapTransactionBuilder::new(
+        &pool_manager,
+        wallet,
+        keys.token_mint_0,
+        keys.token_mint_1,
+        true,
+        quote,
+    );
+
+    let output_token_account = get_associated_token_address_with_program_id(
+        &wallet,
+        &keys.token_mint_1,
+        &spl_token_2022::id(),
+    );
+    assert_eq!(builder.input_token_program().unwrap(), spl_token::id());
+    assert_eq!(
+        builder.output_token_program().unwrap(),
+        spl_token_2022::id()
+    );
+    assert_eq!(
+        builder.output_token_account().unwrap(),
+        output_token_account
+    );
+    let instructions = builder.build_instructions().unwrap();
+    let create_account = instructions
+        .iter()
+        .find(|instruction| instruction.program_id == spl_associated_token_account::id())
+        .unwrap();
+    assert_eq!(create_account.accounts[1].pubkey, output_token_account);
+    assert_eq!(create_account.accounts[5].pubkey, spl_token_2022::id());
+
+    // without the mint's owner there is no telling its program
+    pool_manager.mint1_owner = None;
+    let builder = SwapTransactionBuilder::new(
+        &pool_manager,
+        wallet,
+        keys.token_mint_0,
+        keys.token_mint_1,
+        true,
+        quote,
+    );
+    assert!(builder.output_token_program().is_err());
+    assert!(builder.build_instructions().is_err());
+}